@@ -1,5 +1,8 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use sokoban::game::{board::Board, solver::Solver};
+use sokoban::game::{
+    board::Board,
+    solver::{CancellationToken, DeadlockKind, Solver, SolverConfig},
+};
 use std::fs;
 
 pub fn benchmark(c: &mut Criterion) {
@@ -11,7 +14,7 @@ pub fn benchmark(c: &mut Criterion) {
     group_1.bench_function("solve cognitive 1", |b| {
         b.iter(|| {
             let solver_1 = Solver::new(&board_1);
-            let _ = solver_1.solve(None);
+            let _ = solver_1.solve(&CancellationToken::new());
         })
     });
     group_1.finish();
@@ -24,7 +27,7 @@ pub fn benchmark(c: &mut Criterion) {
     group_2.bench_function("solve cognitive 2", |b| {
         b.iter(|| {
             let solver_2 = Solver::new(&board_2);
-            let _ = solver_2.solve(None);
+            let _ = solver_2.solve(&CancellationToken::new());
         })
     });
     group_2.finish();
@@ -37,7 +40,7 @@ pub fn benchmark(c: &mut Criterion) {
     group_3.bench_function("solve cognitive 3", |b| {
         b.iter(|| {
             let solver_3 = Solver::new(&board_3);
-            let _ = solver_3.solve(None);
+            let _ = solver_3.solve(&CancellationToken::new());
         })
     });
     group_3.finish();
@@ -50,10 +53,32 @@ pub fn benchmark(c: &mut Criterion) {
     group_4.bench_function("solve cognitive 4", |b| {
         b.iter(|| {
             let solver_4 = Solver::new(&board_4);
-            let _ = solver_4.solve(None);
+            let _ = solver_4.solve(&CancellationToken::new());
         })
     });
     group_4.finish();
+
+    // every detector on top of dead squares, to see whether it pays for itself
+    let mut group_5 = c.benchmark_group("deadlock detectors on cognitive 3");
+    group_5.measurement_time(std::time::Duration::new(20, 0));
+    group_5.sample_size(10);
+    for kind in DeadlockKind::ALL {
+        let mut deadlocks = vec![DeadlockKind::DeadSquares];
+        if kind != DeadlockKind::DeadSquares {
+            deadlocks.push(kind);
+        }
+        let config = SolverConfig {
+            deadlocks,
+            ..Default::default()
+        };
+        group_5.bench_function(kind.name(), |b| {
+            b.iter(|| {
+                let solver_5 = Solver::with_config(&board_3, config.clone());
+                let _ = solver_5.solve(&CancellationToken::new());
+            })
+        });
+    }
+    group_5.finish();
 }
 
 criterion_group!(benches, benchmark);
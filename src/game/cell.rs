@@ -27,10 +27,7 @@ impl PrintFullByQueue for Cell {
                 queue!(stdout(), PrintStyledContent("+".green()))
             }
             (Some(e), Grid::Ground) => e.print_full(),
-            _ => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Impossible state!",
-            )),
+            _ => Err(std::io::Error::other("Impossible state!")),
         }
     }
 }
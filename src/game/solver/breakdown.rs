@@ -0,0 +1,218 @@
+use super::{DeltaBoard, Solution};
+use crate::game::board::Board;
+use crate::game::board_command::BoardCommand;
+use crate::game::entity::Entity;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BreakdownError {
+    IllegalMove { step: usize }, // the player would walk into a wall or a stuck box
+}
+
+impl std::fmt::Display for BreakdownError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IllegalMove { step } => write!(f, "Move {} can't be made", step + 1),
+        }
+    }
+}
+
+impl std::error::Error for BreakdownError {}
+
+/// Taking one box from where it stands to where the next box gets pushed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoxRoute {
+    pub id: usize,                 // boxes are numbered by starting square, row by row
+    pub path: Vec<(usize, usize)>, // every square it stands on, in order
+    pub route: Vec<BoardCommand>,  // its pushes, with any walks around it
+}
+
+impl BoxRoute {
+    pub fn from(&self) -> (usize, usize) {
+        self.path[0]
+    }
+
+    pub fn to(&self) -> (usize, usize) {
+        self.path[self.path.len() - 1]
+    }
+
+    pub fn pushes(&self) -> usize {
+        self.path.len() - 1
+    }
+}
+
+/// A walk to some box, then pushing it around until another box needs pushing. Only a walk after the last push
+/// comes without a box.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub walk: Vec<BoardCommand>,
+    pub push: Option<BoxRoute>,
+}
+
+impl Segment {
+    pub fn len(&self) -> usize {
+        self.walk.len() + self.push.as_ref().map_or(0, |push| push.route.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // boxes and squares are counted from 1 here
+        if !self.walk.is_empty() {
+            write!(f, "Walk {}", count(self.walk.len(), "move"))?;
+        }
+        let Some(push) = &self.push else {
+            return Ok(());
+        };
+        match self.walk.is_empty() {
+            true => write!(f, "Push")?,
+            false => write!(f, ", then push")?,
+        }
+        let ((i0, j0), (i1, j1)) = (push.from(), push.to());
+        write!(
+            f,
+            " box #{} from row {}, column {} to row {}, column {} in {}",
+            push.id + 1,
+            i0 + 1,
+            j0 + 1,
+            i1 + 1,
+            j1 + 1,
+            count(push.pushes(), "push")
+        )
+    }
+}
+
+/// "1 move", "2 moves", "1 push", "2 pushes".
+fn count(n: usize, noun: &str) -> String {
+    match (n == 1, noun.ends_with('h')) {
+        (true, _) => format!("{} {}", n, noun),
+        (false, true) => format!("{} {}es", n, noun),
+        (false, false) => format!("{} {}s", n, noun),
+    }
+}
+
+/// Splits `seq` as played on `g` into walks and the boxes pushed after them. Boxes keep the ID of the square they
+/// start on, whichever square they end up on.
+pub fn breakdown(g: &Board, seq: &[BoardCommand]) -> Result<Vec<Segment>, BreakdownError> {
+    let mut ids = vec![usize::MAX; g.n * g.m]; // box standing on `i * m + j`
+    let mut next_id = 0;
+    for (i, row) in g.cells.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate() {
+            if let Some(Entity::Box) = cell.entity {
+                ids[i * g.m + j] = next_id;
+                next_id += 1;
+            }
+        }
+    }
+    let mut h = DeltaBoard::from(g);
+    let mut segments: Vec<Segment> = vec![];
+    let mut walk = vec![]; // moves since the last push
+    for (step, &direction) in seq.iter().enumerate() {
+        let (box_moved, player_moved) = h.execute(direction);
+        if !player_moved {
+            return Err(BreakdownError::IllegalMove { step });
+        }
+        let Some((bi, bj)) = box_moved else {
+            walk.push(direction);
+            continue;
+        };
+        // the player now stands where the box was
+        let id = std::mem::replace(&mut ids[h.i * g.m + h.j], usize::MAX);
+        ids[bi * g.m + bj] = id;
+        match segments.last_mut().and_then(|v| v.push.as_mut()) {
+            Some(push) if push.id == id => {
+                push.route.append(&mut walk);
+                push.route.push(direction);
+                push.path.push((bi, bj));
+            }
+            _ => segments.push(Segment {
+                walk: std::mem::take(&mut walk),
+                push: Some(BoxRoute {
+                    id,
+                    path: vec![(h.i, h.j), (bi, bj)],
+                    route: vec![direction],
+                }),
+            }),
+        }
+    }
+    if !walk.is_empty() {
+        segments.push(Segment { walk, push: None });
+    }
+    Ok(segments)
+}
+
+impl Solution {
+    /// See `breakdown`, `g` being the level this solves.
+    pub fn breakdown(&self, g: &Board) -> Result<Vec<Segment>, BreakdownError> {
+        breakdown(g, &self.seq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{breakdown, BreakdownError};
+    use crate::game::board::Board;
+    use crate::game::board_command::BoardCommand;
+    use crate::game::solver::optimizer::parse_lurd;
+    use crate::game::solver::{test_level, CancellationToken, Solver};
+
+    #[test]
+    fn test_breakdown_0() {
+        let g = Board::from(
+            "#######\n\
+             #@    #\n\
+             # $ $ #\n\
+             # ..  #\n\
+             #######",
+        );
+        let seq = parse_lurd("rDrurrdLulDl").unwrap();
+        let segments = breakdown(&g, &seq).unwrap();
+        assert_eq!(segments.len(), 3);
+        let first = segments[0].push.as_ref().unwrap();
+        assert_eq!((first.id, first.from(), first.to()), (0, (2, 2), (3, 2)));
+        assert_eq!(segments[0].walk, vec![BoardCommand::Right]);
+        // a box keeps its ID through walks around it
+        let second = segments[1].push.as_ref().unwrap();
+        assert_eq!(second.id, 1);
+        assert_eq!(second.path, vec![(2, 4), (2, 3), (3, 3)]);
+        assert_eq!(second.route, parse_lurd("LulD").unwrap());
+        assert_eq!(segments[2].push, None);
+        assert_eq!(segments[2].walk, vec![BoardCommand::Left]);
+        assert_eq!(
+            segments[0].to_string(),
+            "Walk 1 move, then push box #1 from row 3, column 3 to row 4, column 3 in 1 push"
+        );
+        assert_eq!(
+            segments[1].to_string(),
+            "Walk 5 moves, then push box #2 from row 3, column 5 to row 4, column 4 in 2 pushes"
+        );
+        assert_eq!(segments.iter().map(|v| v.len()).sum::<usize>(), seq.len());
+        assert_eq!(
+            breakdown(&g, &parse_lurd("u").unwrap()),
+            Err(BreakdownError::IllegalMove { step: 0 })
+        );
+    }
+
+    #[test]
+    fn test_breakdown_1() {
+        let g = test_level("cognitive/3");
+        let solution = Solver::new(&g).solve(&CancellationToken::new()).unwrap();
+        let segments = solution.breakdown(&g).unwrap();
+        let mut seq = vec![];
+        for segment in segments.iter() {
+            seq.extend_from_slice(&segment.walk);
+            if let Some(push) = &segment.push {
+                seq.extend_from_slice(&push.route);
+            }
+        }
+        assert_eq!(seq, solution.seq);
+        // one box never gets pushed twice in a row
+        for pair in segments.windows(2) {
+            let [a, b] = pair else { unreachable!() };
+            assert_ne!(a.push.as_ref().unwrap().id, b.push.as_ref().unwrap().id);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    deadline: Option<Instant>,
+    parent: Option<CancellationToken>,
+}
+
+/// Tells a running search to stop. Clones share the same flag, so any thread holding one can cancel, and a child
+/// token gets cancelled together with its parent without being able to cancel the parent itself.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that is cancelled whenever this one is, but can also be cancelled on its own.
+    pub fn child(&self) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                parent: Some(self.clone()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Like `child`, but also cancels itself once `timeout` has passed.
+    pub fn child_with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                deadline: Some(Instant::now() + timeout),
+                parent: Some(self.clone()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+            || self
+                .inner
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .inner
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_cancelled())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CancellationToken;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cancel_0() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        let child = token.child();
+        assert!(!token.is_cancelled() && !child.is_cancelled());
+        // cancelling a child leaves the parent alone
+        child.cancel();
+        assert!(child.is_cancelled() && !token.is_cancelled());
+        let other = token.child();
+        clone.cancel();
+        assert!(token.is_cancelled() && other.is_cancelled());
+    }
+
+    #[test]
+    fn test_cancel_1() {
+        let token = CancellationToken::new();
+        let child = token.child_with_timeout(Duration::ZERO);
+        assert!(child.is_cancelled() && !token.is_cancelled());
+        let child = token.child_with_timeout(Duration::from_secs(3600));
+        assert!(!child.is_cancelled());
+    }
+}
@@ -0,0 +1,285 @@
+use super::state_table::StateTable;
+use super::{DeltaBoard, Node, State, DIRECTIONS};
+use crate::game::board::Board;
+use crate::game::board_command::BoardCommand;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MAGIC: &[u8; 8] = b"SOKOCKPT";
+const VERSION: u32 = 1;
+
+/// Where and how often a search saves itself, so that a later run can go on from there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpointing {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+/// Everything a search needs to pick up where it stopped.
+pub(super) struct Snapshot<'a> {
+    pub que: BinaryHeap<Reverse<State<'a>>>,
+    pub visited: StateTable,
+    pub arena: Vec<Node>,
+    pub pruned: Vec<usize>,
+    pub best_h: usize,
+    pub next_seq: u64,
+}
+
+fn command_code(command: BoardCommand) -> u8 {
+    DIRECTIONS
+        .iter()
+        .position(|(c, _)| *c == command)
+        .map_or(4, |k| k as u8)
+}
+
+fn command_from_code(code: u8) -> BoardCommand {
+    DIRECTIONS
+        .get(code as usize)
+        .map_or(BoardCommand::Null, |(c, _)| *c)
+}
+
+pub(super) struct Writer<W: Write>(pub W);
+
+impl<W: Write> Writer<W> {
+    fn u8(&mut self, v: u8) -> std::io::Result<()> {
+        self.0.write_all(&[v])
+    }
+
+    pub(super) fn u16(&mut self, v: u16) -> std::io::Result<()> {
+        self.0.write_all(&v.to_le_bytes())
+    }
+
+    fn u32(&mut self, v: u32) -> std::io::Result<()> {
+        self.0.write_all(&v.to_le_bytes())
+    }
+
+    fn u64(&mut self, v: u64) -> std::io::Result<()> {
+        self.0.write_all(&v.to_le_bytes())
+    }
+
+    fn node(&mut self, node: &Node) -> std::io::Result<()> {
+        self.u32(node.parent)?;
+        self.u32(node.box_pos)?;
+        self.u8(command_code(node.direction))?;
+        self.u16(node.pushes)
+    }
+
+    pub(super) fn state(&mut self, state: &State<'_>) -> std::io::Result<()> {
+        let g = &state.g;
+        self.u32((g.i * g.m + g.j) as u32)?;
+        for &(i, j) in g.boxes.iter() {
+            self.u32((i * g.m + j) as u32)?;
+        }
+        self.node(&state.node)?;
+        self.u64(state.cost as u64)?;
+        self.u64(state.est_rest as u64)?;
+        self.u64(state.priority as u64)?;
+        self.u64(state.seq)
+    }
+}
+
+pub(super) struct Reader<R: Read>(pub R);
+
+impl<R: Read> Reader<R> {
+    fn bytes<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.0.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    pub(super) fn u16(&mut self) -> std::io::Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes()?))
+    }
+
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn usize(&mut self) -> std::io::Result<usize> {
+        Ok(self.u64()? as usize)
+    }
+
+    fn node(&mut self) -> std::io::Result<Node> {
+        Ok(Node {
+            parent: self.u32()?,
+            box_pos: self.u32()?,
+            direction: command_from_code(self.u8()?),
+            pushes: self.u16()?,
+        })
+    }
+
+    /// Reads a state written by `Writer::state`, rebuilding its board from `base`.
+    pub(super) fn state<'a>(&mut self, base: &DeltaBoard<'a>) -> std::io::Result<State<'a>> {
+        let m = base.m;
+        let player = self.u32()? as usize;
+        let boxes = (0..base.num_box)
+            .map(|_| self.u32().map(|idx| (idx as usize / m, idx as usize % m)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(State {
+            g: base.with_boxes(boxes, (player / m, player % m)),
+            node: self.node()?,
+            cost: self.usize()?,
+            est_rest: self.usize()?,
+            priority: self.usize()?,
+            seq: self.u64()?,
+        })
+    }
+}
+
+fn invalid(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+/// Writes `snapshot` to `path`, through a temporary file so that a crash halfway leaves the previous checkpoint
+/// intact, creating the directory it goes in if needed. `fingerprint` identifies the level and settings the search
+/// runs with.
+pub(super) fn save(path: &Path, fingerprint: u64, snapshot: &Snapshot<'_>) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    let mut out = Writer(BufWriter::new(fs::File::create(&tmp)?));
+    out.0.write_all(MAGIC)?;
+    out.u32(VERSION)?;
+    out.u64(fingerprint)?;
+    out.u64(snapshot.next_seq)?;
+    out.u64(snapshot.best_h as u64)?;
+    out.u32(snapshot.pruned.len() as u32)?;
+    for &count in snapshot.pruned.iter() {
+        out.u64(count as u64)?;
+    }
+    out.u64(snapshot.arena.len() as u64)?;
+    for node in snapshot.arena.iter() {
+        out.node(node)?;
+    }
+    out.u64(snapshot.visited.len() as u64)?;
+    for idx in 0..snapshot.visited.len() {
+        for &v in snapshot.visited.key(idx) {
+            out.u16(v)?;
+        }
+    }
+    out.u64(snapshot.que.len() as u64)?;
+    for Reverse(state) in snapshot.que.iter() {
+        out.state(state)?;
+    }
+    out.0.flush()?;
+    drop(out);
+    fs::rename(&tmp, path)
+}
+
+/// Reads a checkpoint written by `save` for the same `fingerprint`, `None` if there is none to resume from.
+pub(super) fn load<'a>(
+    path: &Path,
+    fingerprint: u64,
+    g: &'a Board,
+    num_detectors: usize,
+) -> std::io::Result<Option<Snapshot<'a>>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut input = Reader(BufReader::new(file));
+    if &input.bytes::<8>()? != MAGIC || input.u32()? != VERSION {
+        return Err(invalid("not a solver checkpoint"));
+    }
+    if input.u64()? != fingerprint {
+        // written for another level or other settings
+        return Ok(None);
+    }
+    let next_seq = input.u64()?;
+    let best_h = input.usize()?;
+    let num_pruned = input.u32()? as usize;
+    if num_pruned != num_detectors {
+        return Err(invalid("detector count doesn't match"));
+    }
+    let pruned = (0..num_pruned)
+        .map(|_| input.usize())
+        .collect::<Result<Vec<_>, _>>()?;
+    let arena = (0..input.u64()?)
+        .map(|_| input.node())
+        .collect::<Result<Vec<_>, _>>()?;
+    let stride = g.num_box + 1;
+    let mut visited = StateTable::new(stride);
+    let mut key = vec![0; stride];
+    for _ in 0..input.u64()? {
+        for v in key.iter_mut() {
+            *v = input.u16()?;
+        }
+        visited.insert(&key);
+    }
+    let base = DeltaBoard::from(g);
+    let mut que = BinaryHeap::new();
+    for _ in 0..input.u64()? {
+        que.push(Reverse(input.state(&base)?));
+    }
+    Ok(Some(Snapshot {
+        que,
+        visited,
+        arena,
+        pruned,
+        best_h,
+        next_seq,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::solver::{
+        assert_solves, test_level, CancellationToken, Checkpointing, Solver, SolverConfig,
+        SolverError, SolverLimits,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_checkpoint_0() {
+        let g = test_level("cognitive/3");
+        let path =
+            std::env::temp_dir().join(format!("sokoban_checkpoint_{}.bin", std::process::id()));
+        let uninterrupted = Solver::new(&g).solve(&CancellationToken::new()).unwrap();
+        let config = SolverConfig {
+            checkpoint: Some(Checkpointing {
+                path: path.clone(),
+                interval: Duration::from_secs(3600),
+            }),
+            ..Default::default()
+        };
+        // stop a few times along the way, every run going on from where the last one stopped
+        let mut resumed = None;
+        for _ in 0..20 {
+            let solver = Solver::with_config(
+                &g,
+                SolverConfig {
+                    limits: SolverLimits {
+                        max_states: Some(uninterrupted.visited_states / 4),
+                        ..Default::default()
+                    },
+                    ..config.clone()
+                },
+            );
+            match solver.solve(&CancellationToken::new()) {
+                Err(SolverError::LimitExceeded { .. }) => assert!(path.exists()),
+                res => {
+                    resumed = Some(res.unwrap());
+                    break;
+                }
+            }
+        }
+        assert_eq!(resumed, Some(uninterrupted.clone()));
+        assert_solves(&g, &uninterrupted.seq);
+        // a finished search leaves nothing behind
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,227 @@
+use super::state_table::StateTable;
+use super::{DeltaBoard, Keying, Solver, DIRECTIONS};
+use crate::game::board::Board;
+use crate::game::board_command::BoardCommand;
+use crate::game::grid::Grid;
+use std::collections::VecDeque;
+
+/// An area the player can't walk into, fenced off by boxes and walls. It is a PI-corral when every legal push of a
+/// barrier box goes into the area (I) and the player can get behind each of those boxes right now (P). Some push
+/// into a PI-corral is needed sooner or later, so the solver only has to consider these pushes.
+#[derive(Debug)]
+pub struct Corral {
+    pub squares: Vec<usize>,         // as `i * m + j`
+    pub boxes: Vec<(usize, usize)>,  // boxes on the barrier
+    pub pushes: Vec<(usize, usize)>, // (box, direction) pairs, as `i * m + j` and index into `DIRECTIONS`
+}
+
+/// How many states the sub-search of `Solver::is_corral_deadlock` may look at before giving up.
+const CORRAL_SEARCH_LIMIT: usize = 1000;
+
+impl<'a> Solver<'a> {
+    /// Finds the PI-corral with the fewest pushes, `reachable` being the flood fill of `g.reachable()`.
+    pub(super) fn find_pi_corral(
+        &self,
+        g: &DeltaBoard<'a>,
+        reachable: &[Option<(BoardCommand, usize)>],
+    ) -> Option<Corral> {
+        let is_floor = |(i, j): (usize, usize)| {
+            g.pos_is_valid(i, j) && matches!(g.get_grid_at(i, j), Grid::Ground | Grid::Target)
+        };
+        let mut comp = vec![usize::MAX; g.n * g.m];
+        let mut best: Option<Corral> = None;
+        for start in 0..g.n * g.m {
+            let pos = (start / g.m, start % g.m);
+            if comp[start] != usize::MAX || reachable[start].is_some() || !g.is_free(pos.0, pos.1) {
+                continue;
+            }
+            // flood the unreachable area
+            let mut squares = vec![start];
+            comp[start] = start;
+            let mut que = VecDeque::from([pos]);
+            let mut boxes = vec![];
+            while let Some(pos) = que.pop_front() {
+                for (_, d) in DIRECTIONS {
+                    let (ni, nj) = Board::get_next(pos, d);
+                    if !g.pos_is_valid(ni, nj) {
+                        continue;
+                    }
+                    let idx = ni * g.m + nj;
+                    if g.has_box_at(ni, nj) {
+                        if !boxes.contains(&(ni, nj)) {
+                            boxes.push((ni, nj));
+                        }
+                    } else if g.is_free(ni, nj) && comp[idx] == usize::MAX {
+                        comp[idx] = start;
+                        squares.push(idx);
+                        que.push_back((ni, nj));
+                    }
+                }
+            }
+            // check the barrier
+            let mut pushes = vec![];
+            let is_pi = boxes.iter().all(|&(bi, bj)| {
+                DIRECTIONS.iter().enumerate().all(|(k, &(_, (di, dj)))| {
+                    let ahead = Board::get_next((bi, bj), (di, dj));
+                    let behind = Board::get_next((bi, bj), (di.wrapping_neg(), dj.wrapping_neg()));
+                    if !g.is_free(ahead.0, ahead.1) {
+                        return true;
+                    }
+                    let into = comp[ahead.0 * g.m + ahead.1] == start;
+                    let behind_reachable = g.pos_is_valid(behind.0, behind.1)
+                        && reachable[behind.0 * g.m + behind.1].is_some();
+                    if into {
+                        if behind_reachable {
+                            pushes.push((bi * g.m + bj, k));
+                            true
+                        } else {
+                            // P: the player must be able to do every push into the corral right now
+                            !is_floor(behind)
+                                || comp[behind.0 * g.m + behind.1] == start
+                                    && !g.has_box_at(behind.0, behind.1)
+                        }
+                    } else {
+                        // I: no push may lead out of the corral
+                        !is_floor(behind) || comp[behind.0 * g.m + behind.1] == start
+                    }
+                })
+            });
+            // a corral without anything left to do in it doesn't need to be entered
+            let unfinished = boxes
+                .iter()
+                .any(|&(i, j)| !matches!(g.get_grid_at(i, j), Grid::Target))
+                || squares
+                    .iter()
+                    .any(|&idx| matches!(g.get_grid_at(idx / g.m, idx % g.m), Grid::Target));
+            if is_pi
+                && unfinished
+                && best
+                    .as_ref()
+                    .is_none_or(|best| pushes.len() < best.pushes.len())
+            {
+                best = Some(Corral {
+                    squares,
+                    boxes,
+                    pushes,
+                });
+            }
+        }
+        best
+    }
+
+    /// Tries to prove that `corral` can never be opened up again. Every box but the ones on its barrier is taken off
+    /// the board, then a small search looks for a way to either let the player into the corral or to put all its
+    /// boxes onto goals. Returns `true` only if that search runs dry within its budget.
+    pub(super) fn is_corral_deadlock(&self, g: &DeltaBoard<'a>, corral: &Corral) -> bool {
+        // a barrier that can't be pushed right now may still be freed by moving the boxes in its way, so even then it
+        // takes the search to tell
+        let start = g.retain_boxes(|pos| corral.boxes.contains(&pos));
+        let opened = |h: &DeltaBoard<'a>| {
+            let reachable = h.reachable();
+            h.is_finished() || corral.squares.iter().any(|&idx| reachable[idx].is_some())
+        };
+        if opened(&start) {
+            return false;
+        }
+        let mut visited = StateTable::new(corral.boxes.len() + 1);
+        let mut key = Vec::with_capacity(corral.boxes.len() + 1);
+        self.encode(&start, Keying::Region, &mut key);
+        visited.insert(&key);
+        let mut que = VecDeque::from([start]);
+        while let Some(h) = que.pop_front() {
+            for (mut new_h, _, direction) in Self::get_next_pushes(&h) {
+                let (new_box_pos, _) = new_h.execute(direction);
+                match new_box_pos {
+                    Some((i, j)) if !self.insolvable[i][j] => {}
+                    _ => continue,
+                }
+                self.encode(&new_h, Keying::Region, &mut key);
+                if !visited.insert(&key).1 {
+                    continue;
+                }
+                if opened(&new_h) || visited.len() > CORRAL_SEARCH_LIMIT {
+                    return false;
+                }
+                que.push_back(new_h);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Board, DeltaBoard, Solver};
+    use super::Corral;
+
+    #[test]
+    fn test_pi_corral_0() {
+        // both boxes can only be pushed towards the goals
+        let g = Board::from(
+            "#######\n\
+             #@ $  #\n\
+             #   $.#\n\
+             ###.###\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let h = DeltaBoard::from(&g);
+        let corral = solver.find_pi_corral(&h, &h.reachable()).unwrap();
+        assert_eq!(corral.boxes.len(), 2);
+        assert_eq!(corral.pushes.len(), 2);
+        assert!(!solver.is_corral_deadlock(&h, &corral));
+    }
+
+    #[test]
+    fn test_pi_corral_1() {
+        // the box could just as well be pushed up or down
+        let g = Board::from(
+            "#####\n\
+             #  ##\n\
+             #@$.#\n\
+             #  ##\n\
+             #####",
+        );
+        let solver = Solver::new(&g);
+        let h = DeltaBoard::from(&g);
+        assert!(solver.find_pi_corral(&h, &h.reachable()).is_none());
+    }
+
+    #[test]
+    fn test_corral_deadlock_0() {
+        let g = Board::from(
+            "#######\n\
+             #@  $.#\n\
+             #   $ #\n\
+             #   #.#\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let h = DeltaBoard::from(&g);
+        let corral = solver.find_pi_corral(&h, &h.reachable()).unwrap();
+        assert_eq!(corral.boxes.len(), 2);
+        assert!(solver.is_corral_deadlock(&h, &corral));
+    }
+
+    #[test]
+    fn test_corral_deadlock_1() {
+        // the barrier box can't be pushed yet, but it can once the box below it is out of the way
+        let g = Board::from(
+            "#######\n\
+             ###.###\n\
+             ### ###\n\
+             ###$###\n\
+             #  $ .#\n\
+             #@    #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let h = DeltaBoard::from(&g);
+        let corral = Corral {
+            squares: vec![7 + 3, 2 * 7 + 3],
+            boxes: vec![(3, 3)],
+            pushes: vec![],
+        };
+        assert!(!solver.is_corral_deadlock(&h, &corral));
+    }
+}
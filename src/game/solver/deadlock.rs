@@ -0,0 +1,864 @@
+use super::pattern_db::{pattern_key, Pattern, PatternCell, PatternDatabase};
+use super::{DeltaBoard, Solver, SolverConfig, DIRECTIONS};
+use crate::game::board::Board;
+use crate::game::grid::Grid;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Why a board is lost, along with the boxes it comes down to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeadlockExplanation {
+    pub kind: DeadlockKind,
+    pub boxes: Vec<(usize, usize)>,
+}
+
+/// The deadlock checks the solver can run on every board it generates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeadlockKind {
+    DeadSquares,
+    Freeze,
+    Bipartite,
+    ClosedDiagonal,
+    Corral,
+    Patterns,
+}
+
+impl DeadlockKind {
+    pub const ALL: [Self; 6] = [
+        Self::DeadSquares,
+        Self::Freeze,
+        Self::Bipartite,
+        Self::ClosedDiagonal,
+        Self::Corral,
+        Self::Patterns,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DeadSquares => "dead squares",
+            Self::Freeze => "freeze",
+            Self::Bipartite => "bipartite",
+            Self::ClosedDiagonal => "closed diagonal",
+            Self::Corral => "corral",
+            Self::Patterns => "patterns",
+        }
+    }
+}
+
+impl Display for DeadlockKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A check run right after each push. Detectors must be sound: a board they flag can never be solved.
+pub trait DeadlockDetector: Send + Sync {
+    fn kind(&self) -> DeadlockKind;
+
+    /// Whether `g` is a deadlock, `box_pos` being the square the last pushed box ended up on.
+    fn is_deadlock(&self, solver: &Solver<'_>, g: &DeltaBoard<'_>, box_pos: (usize, usize))
+        -> bool;
+
+    /// Called once the search is over, whatever its outcome.
+    fn finish(&self) {}
+}
+
+/// Some box stands on a square from which no goal can be reached, see `Solver::calc_insolvable`.
+struct DeadSquares;
+
+impl DeadlockDetector for DeadSquares {
+    fn kind(&self) -> DeadlockKind {
+        DeadlockKind::DeadSquares
+    }
+
+    fn is_deadlock(&self, solver: &Solver<'_>, g: &DeltaBoard<'_>, _: (usize, usize)) -> bool {
+        g.boxes.iter().any(|&(i, j)| solver.insolvable[i][j])
+    }
+}
+
+/// The pushed box got frozen off its goal, see `Solver::check_freeze_deadlock`.
+struct Freeze;
+
+impl DeadlockDetector for Freeze {
+    fn kind(&self) -> DeadlockKind {
+        DeadlockKind::Freeze
+    }
+
+    fn is_deadlock(
+        &self,
+        solver: &Solver<'_>,
+        g: &DeltaBoard<'_>,
+        box_pos: (usize, usize),
+    ) -> bool {
+        solver.check_freeze_deadlock(g, box_pos)
+    }
+}
+
+/// Boxes can't all be matched to distinct goals they could still be pushed onto.
+struct Bipartite {
+    goals: Vec<Vec<usize>>, // for each `i * m + j`, the goals a lone box standing there could be pushed onto
+    num_goals: usize,
+}
+
+impl Bipartite {
+    fn new(g: &Board) -> Self {
+        let is_floor = |(i, j): (usize, usize)| {
+            g.pos_is_valid(i, j)
+                && matches!(
+                    g.cells[i].get(j).map(|cell| cell.grid),
+                    Some(Grid::Ground | Grid::Target)
+                )
+        };
+        let mut goals = vec![vec![]; g.n * g.m];
+        let targets: Vec<_> = (0..g.n)
+            .flat_map(|i| (0..g.m).map(move |j| (i, j)))
+            .filter(|&(i, j)| matches!(g.cells[i].get(j).map(|cell| cell.grid), Some(Grid::Target)))
+            .collect();
+        for (k, &target) in targets.iter().enumerate() {
+            // pull a virtual box away from the goal, like `Solver::calc_insolvable` does for all goals at once
+            let mut seen = vec![false; g.n * g.m];
+            seen[target.0 * g.m + target.1] = true;
+            let mut que = VecDeque::from([target]);
+            while let Some(pos) = que.pop_front() {
+                goals[pos.0 * g.m + pos.1].push(k);
+                for (_, d) in DIRECTIONS {
+                    let next = Board::get_next(pos, d);
+                    if is_floor(next)
+                        && is_floor(Board::get_next(next, d))
+                        && !seen[next.0 * g.m + next.1]
+                    {
+                        seen[next.0 * g.m + next.1] = true;
+                        que.push_back(next);
+                    }
+                }
+            }
+        }
+        Self {
+            goals,
+            num_goals: targets.len(),
+        }
+    }
+
+    fn augment(&self, boxes: &[usize], b: usize, owner: &mut [usize], seen: &mut [bool]) -> bool {
+        for &k in self.goals[boxes[b]].iter() {
+            if seen[k] {
+                continue;
+            }
+            seen[k] = true;
+            if owner[k] == usize::MAX || self.augment(boxes, owner[k], owner, seen) {
+                owner[k] = b;
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl DeadlockDetector for Bipartite {
+    fn kind(&self) -> DeadlockKind {
+        DeadlockKind::Bipartite
+    }
+
+    fn is_deadlock(&self, _: &Solver<'_>, g: &DeltaBoard<'_>, _: (usize, usize)) -> bool {
+        // Kuhn's augmenting paths, boxes with the fewest choices first so failures show up early
+        let mut boxes: Vec<_> = g.boxes.iter().map(|&(i, j)| i * g.m + j).collect();
+        boxes.sort_by_key(|&idx| self.goals[idx].len());
+        let mut owner = vec![usize::MAX; self.num_goals];
+        let mut seen = vec![false; self.num_goals];
+        (0..boxes.len()).any(|b| {
+            seen.fill(false);
+            !self.augment(&boxes, b, &mut owner, &mut seen)
+        })
+    }
+}
+
+/// Boxes and walls closing in an empty square next to the pushed box, see `Solver::check_closed_diagonal`.
+struct ClosedDiagonal;
+
+impl DeadlockDetector for ClosedDiagonal {
+    fn kind(&self) -> DeadlockKind {
+        DeadlockKind::ClosedDiagonal
+    }
+
+    fn is_deadlock(
+        &self,
+        solver: &Solver<'_>,
+        g: &DeltaBoard<'_>,
+        box_pos: (usize, usize),
+    ) -> bool {
+        solver.check_closed_diagonal(g, box_pos)
+    }
+}
+
+/// The player is fenced off from an area that can never be opened up again, see `Solver::is_corral_deadlock`.
+struct Corral;
+
+impl DeadlockDetector for Corral {
+    fn kind(&self) -> DeadlockKind {
+        DeadlockKind::Corral
+    }
+
+    fn is_deadlock(&self, solver: &Solver<'_>, g: &DeltaBoard<'_>, _: (usize, usize)) -> bool {
+        solver
+            .find_pi_corral(g, &g.reachable())
+            .is_some_and(|corral| solver.is_corral_deadlock(g, &corral))
+    }
+}
+
+/// The boxes around the pushed box form a pattern from the `PatternDatabase`, learning new patterns as they come.
+struct Patterns {
+    db: Mutex<PatternDatabase>,
+    path: Option<PathBuf>, // where the database gets loaded from and saved to
+}
+
+impl Patterns {
+    /// Windows looked at around the pushed box, as (height, width, row offset, column offset of the box).
+    const WINDOWS: [(usize, usize, usize, usize); 9] = [
+        (2, 2, 0, 0),
+        (2, 2, 0, 1),
+        (2, 2, 1, 0),
+        (2, 2, 1, 1),
+        (3, 3, 1, 1),
+        (4, 4, 1, 1),
+        (4, 4, 1, 2),
+        (4, 4, 2, 1),
+        (4, 4, 2, 2),
+    ];
+
+    fn new(path: Option<PathBuf>) -> Self {
+        // a broken database only costs time, the patterns will be learned again
+        let db = path
+            .as_deref()
+            .and_then(|path| PatternDatabase::load(path).ok())
+            .unwrap_or_default();
+        Self {
+            db: Mutex::new(db),
+            path,
+        }
+    }
+
+    fn window_key(
+        g: &DeltaBoard<'_>,
+        (h, w, di, dj): (usize, usize, usize, usize),
+        box_pos: (usize, usize),
+    ) -> Option<u64> {
+        let mut cells = Vec::with_capacity(h * w);
+        let mut num_box = 0;
+        let mut off_target = false;
+        for k in 0..h * w {
+            let (i, j) = (
+                (box_pos.0 + k / w).wrapping_sub(di),
+                (box_pos.1 + k % w).wrapping_sub(dj),
+            );
+            let grid = match g.pos_is_valid(i, j) {
+                true => g.get_grid_at(i, j),
+                false => Grid::Wall,
+            };
+            let target = matches!(grid, Grid::Target);
+            cells.push(if matches!(grid, Grid::Wall) {
+                PatternCell::Wall
+            } else if g.has_box_at(i, j) {
+                num_box += 1;
+                off_target |= !target;
+                match target {
+                    true => PatternCell::BoxOnGoal,
+                    false => PatternCell::Box,
+                }
+            } else if (i, j) == (g.i, g.j) {
+                match target {
+                    true => PatternCell::PlayerOnGoal,
+                    false => PatternCell::Player,
+                }
+            } else {
+                match target {
+                    true => PatternCell::Goal,
+                    false => PatternCell::Floor,
+                }
+            });
+        }
+        // a single box is the business of the dead squares
+        (num_box >= 2 && off_target).then(|| pattern_key(h, w, &cells))
+    }
+}
+
+impl DeadlockDetector for Patterns {
+    fn kind(&self) -> DeadlockKind {
+        DeadlockKind::Patterns
+    }
+
+    fn is_deadlock(&self, _: &Solver<'_>, g: &DeltaBoard<'_>, box_pos: (usize, usize)) -> bool {
+        let mut db = self.db.lock().unwrap();
+        Self::WINDOWS.into_iter().any(|window| {
+            Self::window_key(g, window, box_pos)
+                .is_some_and(|key| db.lookup(key) == Pattern::Deadlock)
+        })
+    }
+
+    fn finish(&self) {
+        if let Some(path) = &self.path {
+            let _ = self.db.lock().unwrap().save(path);
+        }
+    }
+}
+
+pub(super) fn create_detector(
+    kind: DeadlockKind,
+    g: &Board,
+    config: &SolverConfig,
+) -> Box<dyn DeadlockDetector> {
+    match kind {
+        DeadlockKind::DeadSquares => Box::new(DeadSquares),
+        DeadlockKind::Freeze => Box::new(Freeze),
+        DeadlockKind::Bipartite => Box::new(Bipartite::new(g)),
+        DeadlockKind::ClosedDiagonal => Box::new(ClosedDiagonal),
+        DeadlockKind::Corral => Box::new(Corral),
+        DeadlockKind::Patterns => Box::new(Patterns::new(config.pattern_db.clone())),
+    }
+}
+
+enum Axis {
+    Vert,
+    Horz,
+}
+
+#[derive(Debug)]
+enum Deadlock {
+    No,
+    Maybe(Vec<(usize, usize)>),
+    Yes,
+}
+
+impl<'a> Solver<'a> {
+    fn check_freeze_deadlock_on_axis(
+        &self,
+        g: &DeltaBoard,
+        box_pos: (usize, usize),
+        visited: &mut [Vec<bool>],
+        axis: Axis,
+    ) -> Deadlock {
+        let it = match axis {
+            Axis::Vert => [(usize::MAX, 0), (1, 0)],
+            Axis::Horz => [(0, usize::MAX), (0, 1)],
+        }
+        .into_iter()
+        .map(|d| Board::get_next(box_pos, d))
+        .filter(|&(ni, nj)| g.pos_is_valid(ni, nj));
+        if it.clone().all(|(ni, nj)| {
+            matches!(g.get_grid_at(ni, nj), Grid::Ground | Grid::Target) && !g.has_box_at(ni, nj)
+        }) {
+            Deadlock::No
+        } else if it
+            .clone()
+            .any(|(ni, nj)| matches!(g.get_grid_at(ni, nj), Grid::Wall) || visited[ni][nj])
+        {
+            Deadlock::Yes
+        } else {
+            Deadlock::Maybe(
+                it.clone()
+                    .filter(|&(ni, nj)| g.has_box_at(ni, nj))
+                    .collect(),
+            )
+        }
+    }
+
+    fn check_freeze_deadlock_wrap(
+        &self,
+        g: &DeltaBoard,
+        box_pos: (usize, usize),
+        visited: &mut Vec<Vec<bool>>,
+        any_deadlock: &mut bool,
+    ) -> bool {
+        // http://sokobano.de/wiki/index.php?title=How_to_detect_deadlocks
+        let (i, j) = box_pos;
+        let mut v_deadlock = self.check_freeze_deadlock_on_axis(g, box_pos, visited, Axis::Vert);
+        let mut h_deadlock = self.check_freeze_deadlock_on_axis(g, box_pos, visited, Axis::Horz);
+        let res = loop {
+            if matches!(
+                (&v_deadlock, &h_deadlock),
+                (Deadlock::No, _) | (_, Deadlock::No)
+            ) {
+                break false;
+            }
+            if matches!((&v_deadlock, &h_deadlock), (Deadlock::Yes, Deadlock::Yes)) {
+                break true;
+            }
+            if let Deadlock::Maybe(boxes) = v_deadlock {
+                // resolve maybe...
+                visited[i][j] = true;
+                match boxes.into_iter().any(|next_pos| {
+                    self.check_freeze_deadlock_wrap(g, next_pos, visited, any_deadlock)
+                }) {
+                    true => v_deadlock = Deadlock::Yes,
+                    false => v_deadlock = Deadlock::No,
+                }
+                visited[i][j] = false;
+            }
+            if *any_deadlock {
+                break true;
+            }
+            if let Deadlock::Maybe(boxes) = h_deadlock {
+                visited[i][j] = true;
+                match boxes.into_iter().any(|next_pos| {
+                    self.check_freeze_deadlock_wrap(g, next_pos, visited, any_deadlock)
+                }) {
+                    true => h_deadlock = Deadlock::Yes,
+                    false => h_deadlock = Deadlock::No,
+                }
+                visited[i][j] = false;
+            }
+            if *any_deadlock {
+                break true;
+            }
+        };
+        if res && !matches!(g.get_grid_at(i, j), Grid::Target) {
+            *any_deadlock = true;
+        }
+        res
+    }
+
+    pub(super) fn check_freeze_deadlock(&self, g: &DeltaBoard, box_pos: (usize, usize)) -> bool {
+        let mut any_deadlock = false;
+        let mut visited = vec![vec![false; g.m]; g.n];
+        self.check_freeze_deadlock_wrap(g, box_pos, &mut visited, &mut any_deadlock);
+        any_deadlock
+    }
+
+    fn is_stuck(&self, g: &DeltaBoard, pos: (usize, usize)) -> bool {
+        // a wall, or a box that will never move again
+        if !g.pos_is_valid(pos.0, pos.1) || matches!(g.get_grid_at(pos.0, pos.1), Grid::Wall) {
+            return true;
+        }
+        if !g.has_box_at(pos.0, pos.1) {
+            return false;
+        }
+        let mut any_deadlock = false;
+        let mut visited = vec![vec![false; g.m]; g.n];
+        // a frozen box elsewhere is a deadlock of its own, so counting that as stuck is fine
+        self.check_freeze_deadlock_wrap(g, pos, &mut visited, &mut any_deadlock) || any_deadlock
+    }
+
+    /// Finds an empty square next to `box_pos` that boxes and walls have closed in for good. Each box around it is
+    /// stuck sideways, and the player can't get into the square to push it away, so the only way left to move it is
+    /// into the square, where it freezes. If the square isn't a goal and one of those boxes is off its goal, the board
+    /// is lost.
+    fn find_closed_square(
+        &self,
+        g: &DeltaBoard,
+        box_pos: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        DIRECTIONS.into_iter().find_map(|(_, d)| {
+            let closed = Board::get_next(box_pos, d);
+            if !g.is_free(closed.0, closed.1)
+                || closed == (g.i, g.j)
+                || matches!(g.get_grid_at(closed.0, closed.1), Grid::Target)
+            {
+                return None;
+            }
+            let mut off_target = false;
+            let closed_in = DIRECTIONS.into_iter().all(|(_, (di, dj))| {
+                let (i, j) = Board::get_next(closed, (di, dj));
+                if !g.pos_is_valid(i, j) || matches!(g.get_grid_at(i, j), Grid::Wall) {
+                    return true;
+                }
+                if !g.has_box_at(i, j) {
+                    return false;
+                }
+                off_target |= !matches!(g.get_grid_at(i, j), Grid::Target);
+                self.is_stuck(g, Board::get_next((i, j), (dj, di)))
+                    || self.is_stuck(
+                        g,
+                        Board::get_next((i, j), (dj.wrapping_neg(), di.wrapping_neg())),
+                    )
+            });
+            (closed_in && off_target).then_some(closed)
+        })
+    }
+
+    pub(super) fn check_closed_diagonal(&self, g: &DeltaBoard, box_pos: (usize, usize)) -> bool {
+        self.find_closed_square(g, box_pos).is_some()
+    }
+
+    /// The square closed in by a closed diagonal deadlock on the board the solver was built for, if there is one.
+    pub fn find_closed_diagonal(&self) -> Option<(usize, usize)> {
+        let g = DeltaBoard::from(self.board);
+        g.boxes
+            .iter()
+            .find_map(|&box_pos| self.find_closed_square(&g, box_pos))
+    }
+
+    /// Squares inside the level from which no box can ever be pushed onto a goal, see `Solver::calc_insolvable`.
+    /// Inside means the player could walk there if there were no boxes, which leaves out the floor around the walls.
+    pub fn dead_squares(&self) -> Vec<(usize, usize)> {
+        let g = self.board;
+        let mut inside = vec![vec![false; g.m]; g.n];
+        inside[g.i][g.j] = true;
+        let mut que = VecDeque::from([(g.i, g.j)]);
+        while let Some(pos) = que.pop_front() {
+            for (_, d) in DIRECTIONS {
+                let (ni, nj) = Board::get_next(pos, d);
+                if g.pos_is_valid(ni, nj)
+                    && !matches!(g.cells[ni][nj].grid, Grid::Wall)
+                    && !inside[ni][nj]
+                {
+                    inside[ni][nj] = true;
+                    que.push_back((ni, nj));
+                }
+            }
+        }
+        (0..g.n)
+            .flat_map(|i| (0..g.m).map(move |j| (i, j)))
+            .filter(|&(i, j)| inside[i][j] && self.insolvable[i][j])
+            .collect()
+    }
+
+    /// Boxes on the board the solver was built for that can never be pushed again, on goals or not. Once some box
+    /// is frozen off its goal, the boxes leaning on it count as frozen as well.
+    pub fn frozen_boxes(&self) -> Vec<(usize, usize)> {
+        let g = DeltaBoard::from(self.board);
+        let mut visited = vec![vec![false; g.m]; g.n];
+        g.boxes
+            .iter()
+            .copied()
+            .filter(|&box_pos| {
+                let mut any_deadlock = false;
+                self.check_freeze_deadlock_wrap(&g, box_pos, &mut visited, &mut any_deadlock)
+            })
+            .collect()
+    }
+
+    /// Why the board the solver was built for is lost, trying boxes on dead squares, boxes frozen off their goals,
+    /// closed diagonals and then corrals. Bipartite matching and patterns are left out, as they can't point at the
+    /// boxes to blame.
+    pub fn explain_deadlock(&self) -> Option<DeadlockExplanation> {
+        let g = DeltaBoard::from(self.board);
+        let explain = |kind, boxes: Vec<(usize, usize)>| {
+            (!boxes.is_empty()).then_some(DeadlockExplanation { kind, boxes })
+        };
+        let on_dead_squares = g
+            .boxes
+            .iter()
+            .copied()
+            .filter(|&(i, j)| self.insolvable[i][j])
+            .collect();
+        explain(DeadlockKind::DeadSquares, on_dead_squares)
+            .or_else(|| {
+                let frozen = self
+                    .frozen_boxes()
+                    .into_iter()
+                    .filter(|&(i, j)| !matches!(g.get_grid_at(i, j), Grid::Target))
+                    .collect();
+                explain(DeadlockKind::Freeze, frozen)
+            })
+            .or_else(|| {
+                // the boxes around the closed in square
+                let closed = self.find_closed_diagonal()?;
+                let around = DIRECTIONS
+                    .into_iter()
+                    .map(|(_, d)| Board::get_next(closed, d))
+                    .filter(|&(i, j)| g.pos_is_valid(i, j) && g.has_box_at(i, j))
+                    .collect();
+                explain(DeadlockKind::ClosedDiagonal, around)
+            })
+            .or_else(|| {
+                let corral = self
+                    .find_pi_corral(&g, &g.reachable())
+                    .filter(|corral| self.is_corral_deadlock(&g, corral))?;
+                explain(DeadlockKind::Corral, corral.boxes)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Board, DeltaBoard, Solver};
+    use super::{Bipartite, DeadlockDetector, DeadlockKind, Patterns};
+
+    #[test]
+    fn test_explain_deadlock_0() {
+        // two boxes side by side along the wall hold each other in place
+        let g = Board::from(
+            "#######\n\
+             # $$ .#\n\
+             #     #\n\
+             # .  @#\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        assert_eq!(solver.frozen_boxes(), vec![(1, 2), (1, 3)]);
+        let explanation = solver.explain_deadlock().unwrap();
+        assert_eq!(explanation.kind, DeadlockKind::Freeze);
+        assert_eq!(explanation.boxes, vec![(1, 2), (1, 3)]);
+        let g = Board::from(
+            "#######\n\
+             #@   .#\n\
+             #   $ #\n\
+             #$  #.#\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let dead = solver.dead_squares();
+        assert!(dead.contains(&(3, 1)) && dead.contains(&(1, 1)) && !dead.contains(&(2, 2)));
+        let explanation = solver.explain_deadlock().unwrap();
+        assert_eq!(explanation.kind, DeadlockKind::DeadSquares);
+        assert_eq!(explanation.boxes, vec![(3, 1)]);
+    }
+
+    #[test]
+    fn test_explain_deadlock_1() {
+        let g = Board::from(
+            "#######\n\
+             #@  $.#\n\
+             #   $ #\n\
+             #   #.#\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        assert!(solver.frozen_boxes().is_empty());
+        let explanation = solver.explain_deadlock().unwrap();
+        assert_eq!(explanation.kind, DeadlockKind::Corral);
+        assert_eq!(explanation.boxes.len(), 2);
+        let g = Board::from(
+            "#######\n\
+             #@  $.#\n\
+             #     #\n\
+             #   $.#\n\
+             #######",
+        );
+        assert_eq!(Solver::new(&g).explain_deadlock(), None);
+    }
+
+    #[test]
+    fn test_bipartite_0() {
+        // both boxes are stuck along the top wall, where there is only one goal for them
+        let g = Board::from(
+            "#######\n\
+             # $$ .#\n\
+             #     #\n\
+             # .  @#\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let h = DeltaBoard::from(&g);
+        assert!(!solver.insolvable[1][2] && !solver.insolvable[1][3]);
+        assert!(Bipartite::new(&g).is_deadlock(&solver, &h, (1, 3)));
+    }
+
+    #[test]
+    fn test_bipartite_1() {
+        let g = Board::from(
+            "#######\n\
+             # $  .#\n\
+             #  $  #\n\
+             # .  @#\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let h = DeltaBoard::from(&g);
+        assert!(!Bipartite::new(&g).is_deadlock(&solver, &h, (2, 3)));
+    }
+
+    #[test]
+    fn test_patterns_0() {
+        // neither box is on a dead square, but together against the wall they can't move
+        let g = Board::from(
+            "#######\n\
+             # $$ .#\n\
+             #     #\n\
+             #.  @ #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let h = DeltaBoard::from(&g);
+        assert!(!solver.insolvable[1][2] && !solver.insolvable[1][3]);
+        let patterns = Patterns::new(None);
+        assert!(patterns.is_deadlock(&solver, &h, (1, 3)));
+        assert!(!patterns.db.lock().unwrap().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod freeze_deadlock_tests {
+    use super::super::{Board, DeltaBoard, Solver};
+
+    #[test]
+    fn test_freeze_deadlock_0() {
+        let g = Board::from(
+            "########\n\
+             #   #  #\n\
+             #  *.$@#\n\
+             #.$*   #\n\
+             ###    #\n\
+             ########",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_freeze_deadlock(&dg, (2, 5)));
+    }
+
+    #[test]
+    fn test_freeze_deadlock_1() {
+        let g = Board::from(
+            "########\n\
+             #   #  #\n\
+             #  **@ #\n\
+             #.$*   #\n\
+             ###    #\n\
+             ########",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(solver.check_freeze_deadlock(&dg, (2, 4)));
+    }
+
+    #[test]
+    fn test_freeze_deadlock_2() {
+        let g = Board::from(
+            "#######\n\
+             #  *  #\n\
+             #    *#\n\
+             #    @#\n\
+             #     #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_freeze_deadlock(&dg, (2, 5)));
+    }
+
+    #[test]
+    fn test_freeze_deadlock_3() {
+        let g = Board::from(
+            "#######\n\
+             #     #\n\
+             # .$# #\n\
+             # **  #\n\
+             # +$  #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_freeze_deadlock(&dg, (4, 3)));
+    }
+
+    #[test]
+    fn test_freeze_deadlock_4() {
+        let g = Board::from(
+            "#######\n\
+             #     #\n\
+             # **  #\n\
+             # **@ #\n\
+             #     #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_freeze_deadlock(&dg, (3, 3)));
+    }
+
+    #[test]
+    fn test_freeze_deadlock_5() {
+        let g = Board::from(
+            "#######\n\
+             #     #\n\
+             # **  #\n\
+             # **@ #\n\
+             #.$ # #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(solver.check_freeze_deadlock(&dg, (4, 3)));
+    }
+}
+
+#[cfg(test)]
+mod closed_diagonal_tests {
+    use super::super::{Board, DeltaBoard, Solver};
+    #[test]
+    fn test_closed_diagonal_0() {
+        // neither box is frozen, but pushing one into the corner is the only move left for it
+        let g = Board::from(
+            "########\n\
+             ########\n\
+             ## $  .#\n\
+             ##$    #\n\
+             #   @ .#\n\
+             ########",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_freeze_deadlock(&dg, (2, 3)));
+        assert!(!solver.check_freeze_deadlock(&dg, (3, 2)));
+        assert!(solver.check_closed_diagonal(&dg, (2, 3)));
+        assert!(solver.check_closed_diagonal(&dg, (3, 2)));
+        assert_eq!(solver.find_closed_diagonal(), Some((2, 2)));
+    }
+
+    #[test]
+    fn test_closed_diagonal_1() {
+        // a box can still be pushed up out of the way
+        let g = Board::from(
+            "########\n\
+             ### ####\n\
+             ## $  .#\n\
+             ##$    #\n\
+             #   @ .#\n\
+             ########",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_closed_diagonal(&dg, (2, 3)));
+        assert_eq!(solver.find_closed_diagonal(), None);
+    }
+
+    #[test]
+    fn test_closed_diagonal_2() {
+        // the closed in square is a goal, a box may well go there
+        let g = Board::from(
+            "########\n\
+             ########\n\
+             ##.$   #\n\
+             ##$    #\n\
+             #   @ .#\n\
+             ########",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_closed_diagonal(&dg, (2, 3)));
+    }
+
+    #[test]
+    fn test_closed_diagonal_3() {
+        // the player stands in the square and can push the boxes away
+        let g = Board::from(
+            "########\n\
+             ########\n\
+             ##@$  .#\n\
+             ##$    #\n\
+             #     .#\n\
+             ########",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_closed_diagonal(&dg, (2, 3)));
+    }
+
+    #[test]
+    fn test_closed_diagonal_4() {
+        // the right box is kept from moving down by a box frozen on its goal rather than by a wall
+        let g = Board::from(
+            "#######\n\
+             # #   #\n\
+             #* $  #\n\
+             ###*  #\n\
+             #. #@ #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let dg = DeltaBoard::from(&g);
+        assert!(!solver.check_freeze_deadlock(&dg, (2, 3)));
+        assert!(solver.check_closed_diagonal(&dg, (2, 3)));
+    }
+}
@@ -0,0 +1,268 @@
+use super::state_table::StateTable;
+use super::{
+    direction_delta, CancellationToken, DeltaBoard, Keying, Node, SearchStats, Solution, Solver,
+    SolverError, State,
+};
+use crate::game::board::Board;
+use crate::game::board_command::BoardCommand;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::Instant;
+
+impl Solver<'_> {
+    /// Finds up to `k` solutions, shortest first. See `solve_all_optimal` for what counts as different solutions.
+    pub fn solve_k_best(
+        &self,
+        k: usize,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Solution>, SolverError> {
+        self.enumerate(k, false, cancel)
+    }
+
+    /// Finds every solution with the fewest moves, but no more than `limit` of them. Solutions are told apart by
+    /// their pushes alone, the walks in between are always the shortest ones, so two solutions never differ only in
+    /// the way the player walks.
+    pub fn solve_all_optimal(
+        &self,
+        limit: usize,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Solution>, SolverError> {
+        self.enumerate(limit, true, cancel)
+    }
+
+    fn enumerate(
+        &self,
+        max: usize,
+        only_optimal: bool,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Solution>, SolverError> {
+        let res = self.search_all(max, only_optimal, cancel);
+        for detector in self.detectors.iter() {
+            detector.finish();
+        }
+        res
+    }
+
+    /// A* over single pushes in which every state may be expanded up to `max` times, so that the `max` shortest ways
+    /// to any state are all followed, leaving out those that come back to a position they have been in. When only
+    /// optimal solutions are wanted, a state is only expanded at the lowest cost it has been reached with. PI-corrals,
+    /// tunnels and goal rooms are left out, as they drop solutions that are as good as the ones they keep. Deadlock
+    /// detectors only drop boards no solution goes through.
+    fn search_all(
+        &self,
+        max: usize,
+        only_optimal: bool,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Solution>, SolverError> {
+        let start = Instant::now();
+        let limits = self.config.limits;
+        let init_delta_board = DeltaBoard::from(self.board);
+        let est_rest = self
+            .calc_est_rest(&init_delta_board)
+            .map_err(|pos| SolverError::UnreachableBox { pos })?;
+        let mut que = BinaryHeap::from([Reverse(State {
+            g: init_delta_board,
+            node: Node {
+                parent: Node::ROOT,
+                box_pos: 0,
+                direction: BoardCommand::Null,
+                pushes: 0,
+            },
+            cost: 0,
+            est_rest,
+            priority: est_rest,
+            seq: 0,
+        })]);
+        let mut visited = StateTable::new(self.board.num_box + 1);
+        let mut expansions: Vec<(u32, usize)> = vec![]; // per state, how often and at which cost first
+        let mut arena = vec![];
+        let mut positions = vec![]; // `position_hash` of every board in `arena`
+        let mut pruned = vec![0; self.detectors.len()];
+        let mut solutions: Vec<Solution> = vec![];
+        let mut next_seq = 1;
+        let mut best_h = usize::MAX;
+        let mut key = Vec::with_capacity(self.board.num_box + 1);
+        while let Some(Reverse(State {
+            g: h,
+            node,
+            cost,
+            est_rest,
+            ..
+        })) = que.pop()
+        {
+            if cancel.is_cancelled() {
+                return Err(SolverError::Cancelled);
+            }
+            let memory = visited.memory_usage()
+                + arena.capacity() * std::mem::size_of::<Node>()
+                + que.len() * self.state_bytes();
+            if let Some(limit) = limits.exceeded(arena.len(), start.elapsed(), memory) {
+                let stats = SearchStats {
+                    expanded: arena.len(),
+                    frontier: que.len(),
+                    best_h,
+                    elapsed: start.elapsed(),
+                };
+                return Err(SolverError::LimitExceeded { limit, stats });
+            }
+            if only_optimal
+                && solutions
+                    .first()
+                    .is_some_and(|best| cost + est_rest > best.seq.len())
+            {
+                // everything left is longer
+                break;
+            }
+            self.encode(&h, Keying::Exact, &mut key);
+            let (idx, new) = visited.insert(&key);
+            if new {
+                expansions.push((0, cost));
+            }
+            let (count, first_cost) = &mut expansions[idx];
+            // every part of an optimal solution is the shortest way to where it ends
+            if *count as usize >= max || (only_optimal && cost > *first_cost) {
+                continue;
+            }
+            *count += 1;
+            best_h = best_h.min(est_rest);
+            arena.push(node);
+            positions.push(h.position_hash());
+            if h.is_finished() {
+                let seq = self.rebuild_steps(&arena, arena.len() - 1);
+                solutions.push(Solution {
+                    optimal: solutions
+                        .first()
+                        .is_none_or(|best| best.seq.len() == seq.len()),
+                    seq,
+                    visited_states: arena.len(),
+                    pruned: self
+                        .detectors
+                        .iter()
+                        .map(|detector| detector.kind())
+                        .zip(pruned.iter().copied())
+                        .collect(),
+                });
+                if solutions.len() >= max {
+                    break;
+                }
+                continue;
+            }
+            let parent = (arena.len() - 1) as u32;
+            for (mut new_h, walk_len, direction) in Self::get_next_pushes_from(&h, &h.reachable()) {
+                let (bi, bj) = Board::get_next((new_h.i, new_h.j), direction_delta(direction));
+                let (Some(box_pos), true) = new_h.execute(direction) else {
+                    continue;
+                };
+                if let Some(k) = self
+                    .detectors
+                    .iter()
+                    .position(|detector| detector.is_deadlock(self, &new_h, box_pos))
+                {
+                    pruned[k] += 1;
+                    continue;
+                }
+                // coming back to an earlier position makes a longer copy of another solution
+                if !only_optimal && on_path(&arena, &positions, parent, new_h.position_hash()) {
+                    continue;
+                }
+                let Ok(new_est_rest) = self.calc_est_rest(&new_h) else {
+                    continue;
+                };
+                let new_cost = cost + walk_len + 1;
+                que.push(Reverse(State {
+                    g: new_h,
+                    node: Node {
+                        parent,
+                        box_pos: (bi * self.board.m + bj) as u32,
+                        direction,
+                        pushes: 1,
+                    },
+                    cost: new_cost,
+                    est_rest: new_est_rest,
+                    priority: new_cost + new_est_rest,
+                    seq: next_seq,
+                }));
+                next_seq += 1;
+            }
+        }
+        match solutions.is_empty() {
+            true => Err(SolverError::Unsolvable),
+            false => Ok(solutions),
+        }
+    }
+}
+
+/// Whether `position` comes up anywhere on the way from the root to `node`.
+fn on_path(arena: &[Node], positions: &[u64], mut node: u32, position: u64) -> bool {
+    while node != Node::ROOT {
+        if positions[node as usize] == position {
+            return true;
+        }
+        node = arena[node as usize].parent;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::Board;
+    use crate::game::solver::{assert_solves, CancellationToken, Solver, SolverError};
+
+    #[test]
+    fn test_enumerate_0() {
+        // the box goes down then right, or right then down
+        let g = Board::from(
+            "#####\n\
+             #@  #\n\
+             # $ #\n\
+             #  .#\n\
+             #####",
+        );
+        let solver = Solver::new(&g);
+        let all = solver
+            .solve_all_optimal(100, &CancellationToken::new())
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert_ne!(all[0].seq, all[1].seq);
+        for solution in all.iter() {
+            assert_eq!(solution.seq.len(), 5);
+            assert!(solution.optimal);
+            assert_solves(&g, &solution.seq);
+        }
+        let best = solver.solve_k_best(1, &CancellationToken::new()).unwrap();
+        assert_eq!(best.len(), 1);
+        assert_eq!(best[0], all[0]);
+    }
+
+    #[test]
+    fn test_enumerate_1() {
+        let g = Board::from(
+            "#######\n\
+             #  @  #\n\
+             # $#$ #\n\
+             #  .  #\n\
+             #  .  #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        let best = solver.solve_k_best(6, &CancellationToken::new()).unwrap();
+        let lens = best.iter().map(|v| v.seq.len()).collect::<Vec<_>>();
+        assert_eq!(lens, vec![14, 14, 15, 15, 18, 18]);
+        assert!(best.iter().all(|v| v.optimal == (v.seq.len() == 14)));
+        for (k, solution) in best.iter().enumerate() {
+            assert_solves(&g, &solution.seq);
+            assert!(best[..k].iter().all(|v| v.seq != solution.seq));
+        }
+        let g = Board::from(
+            "#######\n\
+             #. @ .#\n\
+             # $#$ #\n\
+             #  #  #\n\
+             #######",
+        );
+        assert_eq!(
+            Solver::new(&g).solve_k_best(3, &CancellationToken::new()),
+            Err(SolverError::Unsolvable)
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use super::checkpoint::{Reader, Writer};
+use super::state_table::StateTable;
+use super::{DeltaBoard, Keying, Solver, State};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Lets a search move its visited set and open list to disk once they outgrow `threshold` bytes, instead of running
+/// out of memory. The parent pointers of expanded states stay in memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalMemory {
+    pub dir: PathBuf, // every search gets its own directory in here, removed when it ends
+    pub threshold: usize, // bytes the visited set and the open list may each hold before they spill
+}
+
+/// Merged into one once there are more of them, which keeps lookups and open files in check.
+const MAX_VISITED_RUNS: usize = 8;
+const MAX_OPEN_RUNS: usize = 16;
+
+/// Tells apart the directories of searches running at the same time.
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// Queued states on disk in the order the open list hands them out, read back one at a time.
+struct OpenRun<'a> {
+    path: PathBuf,
+    input: Reader<BufReader<File>>,
+    left: u64, // states in the file after `head`
+    head: Option<State<'a>>,
+}
+
+impl<'a> OpenRun<'a> {
+    fn create(path: PathBuf, states: &[State<'a>], base: &DeltaBoard<'a>) -> std::io::Result<Self> {
+        let mut out = Writer(BufWriter::new(File::create(&path)?));
+        for state in states.iter() {
+            out.state(state)?;
+        }
+        out.0.flush()?;
+        drop(out);
+        Self::open(path, states.len() as u64, base)
+    }
+
+    fn open(path: PathBuf, len: u64, base: &DeltaBoard<'a>) -> std::io::Result<Self> {
+        let mut run = Self {
+            input: Reader(BufReader::new(File::open(&path)?)),
+            path,
+            left: len,
+            head: None,
+        };
+        run.advance(base)?;
+        Ok(run)
+    }
+
+    /// Takes the head and reads the next state in its place.
+    fn advance(&mut self, base: &DeltaBoard<'a>) -> std::io::Result<Option<State<'a>>> {
+        let next = match self.left {
+            0 => None,
+            _ => {
+                self.left -= 1;
+                Some(self.input.state(base)?)
+            }
+        };
+        Ok(std::mem::replace(&mut self.head, next))
+    }
+}
+
+/// The parts of a search that went to disk, see `ExternalMemory`.
+///
+/// Expanded states leave memory as runs of visited-set keys sorted by key, queued states as runs sorted by priority.
+/// New states are only checked against the visited states still in memory. Checking them against the runs on disk
+/// is delayed until they spill themselves or the visited set does, when it can be done in one sorted pass per run.
+/// Until then, a state that is already on disk may get expanded again.
+pub(super) struct Spill<'a, 's> {
+    solver: &'s Solver<'a>,
+    keying: Keying, // how the search keys its visited set
+    base: DeltaBoard<'a>,
+    dir: PathBuf,
+    threshold: usize,
+    state_bytes: usize,
+    visited_runs: Vec<PathBuf>,
+    open_runs: Vec<OpenRun<'a>>,
+    next_file: usize,
+    pub expanded: usize, // states in the visited runs
+    pub queued: usize,   // states in the open runs
+}
+
+impl<'a, 's> Spill<'a, 's> {
+    pub fn new(
+        solver: &'s Solver<'a>,
+        config: &ExternalMemory,
+        state_bytes: usize,
+        keying: Keying,
+    ) -> std::io::Result<Self> {
+        let dir = config.dir.join(format!(
+            "sokoban_spill_{}_{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            solver,
+            keying,
+            base: DeltaBoard::from(solver.board),
+            dir,
+            threshold: config.threshold,
+            state_bytes,
+            visited_runs: vec![],
+            open_runs: vec![],
+            next_file: 0,
+            expanded: 0,
+            queued: 0,
+        })
+    }
+
+    fn next_path(&mut self, kind: &str) -> PathBuf {
+        self.next_file += 1;
+        self.dir.join(format!("{}_{}.bin", kind, self.next_file))
+    }
+
+    /// Takes the best queued state, wherever it is.
+    pub fn pop(
+        &mut self,
+        que: &mut BinaryHeap<Reverse<State<'a>>>,
+    ) -> std::io::Result<Option<State<'a>>> {
+        let best_run = (0..self.open_runs.len())
+            .filter(|&k| self.open_runs[k].head.is_some())
+            .min_by(|&a, &b| self.open_runs[a].head.cmp(&self.open_runs[b].head));
+        let Some(k) = best_run.filter(|&k| {
+            que.peek()
+                .is_none_or(|Reverse(top)| self.open_runs[k].head.as_ref() < Some(top))
+        }) else {
+            return Ok(que.pop().map(|Reverse(state)| state));
+        };
+        self.queued -= 1;
+        let state = self.open_runs[k].advance(&self.base)?;
+        if self.open_runs[k].head.is_none() {
+            let run = self.open_runs.swap_remove(k);
+            fs::remove_file(&run.path)?;
+        }
+        Ok(state)
+    }
+
+    /// Moves whatever outgrew the threshold to disk.
+    pub fn balance(
+        &mut self,
+        visited: &mut StateTable,
+        que: &mut BinaryHeap<Reverse<State<'a>>>,
+    ) -> std::io::Result<()> {
+        if visited.memory_usage() >= self.threshold {
+            self.flush_visited(visited, que)?;
+        }
+        if que.len() * self.state_bytes >= self.threshold {
+            self.spill_open(visited, que)?;
+        }
+        Ok(())
+    }
+
+    fn key_of(&self, state: &State<'_>) -> Vec<u16> {
+        let mut key = vec![];
+        self.solver.encode(&state.g, self.keying, &mut key);
+        key
+    }
+
+    /// Drops every state that has been expanded already, going by the visited set in memory and the runs on disk.
+    fn drop_visited(
+        &self,
+        states: Vec<State<'a>>,
+        visited: &StateTable,
+    ) -> std::io::Result<Vec<State<'a>>> {
+        let keys = states
+            .iter()
+            .map(|state| self.key_of(state))
+            .collect::<Vec<_>>();
+        let mut seen = keys
+            .iter()
+            .map(|key| visited.contains(key))
+            .collect::<Vec<_>>();
+        let mut order = (0..keys.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+        for path in self.visited_runs.iter() {
+            // both sides are sorted, so one pass over the run does
+            let mut run = KeyRun::open(path, visited.stride())?;
+            for &k in order.iter() {
+                while run.head.as_ref().is_some_and(|head| *head < keys[k]) {
+                    run.advance()?;
+                }
+                seen[k] |= run.head.as_ref() == Some(&keys[k]);
+            }
+        }
+        Ok(states
+            .into_iter()
+            .zip(seen)
+            .filter_map(|(state, seen)| (!seen).then_some(state))
+            .collect())
+    }
+
+    /// Writes the worse half of the open list to disk.
+    fn spill_open(
+        &mut self,
+        visited: &StateTable,
+        que: &mut BinaryHeap<Reverse<State<'a>>>,
+    ) -> std::io::Result<()> {
+        let mut states = std::mem::take(que)
+            .into_vec()
+            .into_iter()
+            .map(|Reverse(state)| state)
+            .collect::<Vec<_>>();
+        states.sort();
+        let spilled = self.drop_visited(states.split_off(states.len() / 2), visited)?;
+        *que = states.into_iter().map(Reverse).collect();
+        if spilled.is_empty() {
+            return Ok(());
+        }
+        let path = self.next_path("open");
+        self.queued += spilled.len();
+        self.open_runs
+            .push(OpenRun::create(path, &spilled, &self.base)?);
+        if self.open_runs.len() > MAX_OPEN_RUNS {
+            self.merge_open_runs()?;
+        }
+        Ok(())
+    }
+
+    fn merge_open_runs(&mut self) -> std::io::Result<()> {
+        let path = self.next_path("open");
+        let mut out = Writer(BufWriter::new(File::create(&path)?));
+        let mut runs = std::mem::take(&mut self.open_runs);
+        let mut count = 0;
+        while let Some(k) = (0..runs.len())
+            .filter(|&k| runs[k].head.is_some())
+            .min_by(|&a, &b| runs[a].head.cmp(&runs[b].head))
+        {
+            out.state(&runs[k].advance(&self.base)?.unwrap())?;
+            count += 1;
+        }
+        out.0.flush()?;
+        drop(out);
+        for run in runs.iter() {
+            fs::remove_file(&run.path)?;
+        }
+        self.open_runs.push(OpenRun::open(path, count, &self.base)?);
+        Ok(())
+    }
+
+    /// Writes the visited set to disk and starts a new one, after taking everything it contains out of the open
+    /// list.
+    fn flush_visited(
+        &mut self,
+        visited: &mut StateTable,
+        que: &mut BinaryHeap<Reverse<State<'a>>>,
+    ) -> std::io::Result<()> {
+        // states queued since the last flush haven't been checked against the runs on disk yet
+        let states = std::mem::take(que)
+            .into_vec()
+            .into_iter()
+            .map(|Reverse(state)| state)
+            .collect::<Vec<_>>();
+        *que = self
+            .drop_visited(states, visited)?
+            .into_iter()
+            .map(Reverse)
+            .collect();
+        // the open runs were checked against the runs on disk when they were written
+        for k in 0..self.open_runs.len() {
+            self.filter_open_run(k, visited)?;
+        }
+        let (open_runs, empty): (Vec<_>, Vec<_>) = std::mem::take(&mut self.open_runs)
+            .into_iter()
+            .partition(|run| run.head.is_some());
+        for run in empty.iter() {
+            fs::remove_file(&run.path)?;
+        }
+        self.open_runs = open_runs;
+        let mut keys = (0..visited.len())
+            .map(|idx| visited.key(idx))
+            .collect::<Vec<_>>();
+        keys.sort();
+        let path = self.next_path("visited");
+        write_keys(&path, keys.into_iter())?;
+        self.expanded += visited.len();
+        self.visited_runs.push(path);
+        *visited = StateTable::new(visited.stride());
+        if self.visited_runs.len() > MAX_VISITED_RUNS {
+            self.merge_visited_runs(visited.stride())?;
+        }
+        Ok(())
+    }
+
+    fn filter_open_run(&mut self, k: usize, visited: &StateTable) -> std::io::Result<()> {
+        let path = self.next_path("open");
+        let mut out = Writer(BufWriter::new(File::create(&path)?));
+        self.queued -= self.open_runs[k].left as usize + 1;
+        let mut count = 0;
+        while let Some(state) = self.open_runs[k].advance(&self.base)? {
+            if !visited.contains(&self.key_of(&state)) {
+                out.state(&state)?;
+                count += 1;
+            }
+        }
+        out.0.flush()?;
+        drop(out);
+        let old = std::mem::replace(
+            &mut self.open_runs[k],
+            OpenRun::open(path, count, &self.base)?,
+        );
+        fs::remove_file(&old.path)?;
+        self.queued += count as usize;
+        Ok(())
+    }
+
+    fn merge_visited_runs(&mut self, stride: usize) -> std::io::Result<()> {
+        let mut runs = self
+            .visited_runs
+            .iter()
+            .map(|path| KeyRun::open(path, stride))
+            .collect::<Result<Vec<_>, _>>()?;
+        let path = self.next_path("visited");
+        let mut out = Writer(BufWriter::new(File::create(&path)?));
+        let mut last: Option<Vec<u16>> = None;
+        self.expanded = 0;
+        // a state expanded again shows up in more than one run, it's only kept once
+        while let Some(k) = (0..runs.len())
+            .filter(|&k| runs[k].head.is_some())
+            .min_by(|&a, &b| runs[a].head.cmp(&runs[b].head))
+        {
+            let key = runs[k].advance()?.unwrap();
+            if last.as_ref() != Some(&key) {
+                for &v in key.iter() {
+                    out.u16(v)?;
+                }
+                self.expanded += 1;
+                last = Some(key);
+            }
+        }
+        out.0.flush()?;
+        for old in std::mem::replace(&mut self.visited_runs, vec![path]) {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Spill<'_, '_> {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn write_keys<'k>(path: &Path, keys: impl Iterator<Item = &'k [u16]>) -> std::io::Result<()> {
+    let mut out = Writer(BufWriter::new(File::create(path)?));
+    for key in keys {
+        for &v in key.iter() {
+            out.u16(v)?;
+        }
+    }
+    out.0.flush()
+}
+
+/// Visited-set keys on disk, read back in ascending order.
+struct KeyRun {
+    input: Reader<BufReader<File>>,
+    stride: usize,
+    head: Option<Vec<u16>>,
+}
+
+impl KeyRun {
+    fn open(path: &Path, stride: usize) -> std::io::Result<Self> {
+        let mut run = Self {
+            input: Reader(BufReader::new(File::open(path)?)),
+            stride,
+            head: None,
+        };
+        run.advance()?;
+        Ok(run)
+    }
+
+    fn advance(&mut self) -> std::io::Result<Option<Vec<u16>>> {
+        let mut key = Vec::with_capacity(self.stride);
+        for _ in 0..self.stride {
+            match self.input.u16() {
+                Ok(v) => key.push(v),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && key.is_empty() => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let next = (!key.is_empty()).then_some(key);
+        Ok(std::mem::replace(&mut self.head, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExternalMemory;
+    use crate::game::board::Board;
+    use crate::game::solver::{
+        assert_solves, test_level, CancellationToken, SearchStrategy, Solver, SolverConfig,
+        SolverError, SolverLimits,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_external_0() {
+        let g = test_level("cognitive/3");
+        let dir = std::env::temp_dir().join(format!("sokoban_external_{}", std::process::id()));
+        let in_memory = Solver::new(&g).solve(&CancellationToken::new()).unwrap();
+        for threshold in [8 << 10, 32 << 10] {
+            let solver = Solver::with_config(
+                &g,
+                SolverConfig {
+                    external: Some(ExternalMemory {
+                        dir: dir.clone(),
+                        threshold,
+                    }),
+                    ..Default::default()
+                },
+            );
+            let res = solver.solve(&CancellationToken::new()).unwrap();
+            assert_eq!(res.seq.len(), in_memory.seq.len());
+            assert_solves(&g, &res.seq);
+            assert!(res.optimal);
+            // states expanded again before their duplicates on disk were found
+            assert!(res.visited_states >= in_memory.visited_states);
+        }
+        let g = Board::from(
+            "#######\n\
+             #. @ .#\n\
+             # $#$ #\n\
+             #  #  #\n\
+             #######",
+        );
+        // the lower left goal is out of reach behind the pillar
+        let pillar = Board::from(
+            "#########\n\
+             #@      #\n\
+             #  $ $  #\n\
+             ##      #\n\
+             #. #   .#\n\
+             #########",
+        );
+        for g in [g, pillar] {
+            let solver = Solver::with_config(
+                &g,
+                SolverConfig {
+                    // everything goes to disk after every single state
+                    external: Some(ExternalMemory {
+                        dir: dir.clone(),
+                        threshold: 0,
+                    }),
+                    strategy: SearchStrategy::AStar,
+                    // far longer than either level takes, so going around in circles shows up as a failure
+                    limits: SolverLimits {
+                        timeout: Some(Duration::from_secs(60)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            );
+            assert_eq!(
+                solver.solve(&CancellationToken::new()),
+                Err(SolverError::Unsolvable)
+            );
+        }
+        // every search cleans up after itself
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+use super::DIRECTIONS;
+use crate::game::board::Board;
+use crate::game::board_command::BoardCommand;
+use crate::game::entity::Entity;
+use crate::game::grid::Grid;
+use std::collections::{HashMap, VecDeque};
+
+/// One straight run of a precomputed route: the box standing at `box_pos` gets pushed `pushes` times along
+/// `direction`, the player walking behind it first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub box_pos: (usize, usize),
+    pub direction: BoardCommand,
+    pub pushes: u16,
+}
+
+/// A dead-end area holding goals that can only be entered through a single square. Once a box is pushed in, the only
+/// sensible thing to do is bringing it to the next goal in `fill_order`, so the solver does that as one macro move.
+#[derive(Debug)]
+pub struct GoalRoom {
+    pub entrance: (usize, usize),
+    pub first: (usize, usize), // the square inside the room next to `entrance`
+    pub squares: Vec<(usize, usize)>,
+    pub fill_order: Vec<(usize, usize)>,
+    pub routes: Vec<Vec<Segment>>, // `routes[k]` brings a box from `first` to `fill_order[k]`
+}
+
+fn is_floor(g: &Board, (i, j): (usize, usize)) -> bool {
+    // rows may be ragged, anything past the end of a row counts as outside
+    g.pos_is_valid(i, j)
+        && matches!(
+            g.cells[i].get(j).map(|cell| cell.grid),
+            Some(Grid::Ground | Grid::Target)
+        )
+}
+
+fn components_without(g: &Board, cut: (usize, usize)) -> Vec<Vec<(usize, usize)>> {
+    // connected floor components next to `cut` once `cut` itself has been walled up
+    let mut seen = vec![vec![false; g.m]; g.n];
+    seen[cut.0][cut.1] = true;
+    let mut res = vec![];
+    for (_, d) in DIRECTIONS {
+        let start = Board::get_next(cut, d);
+        if !is_floor(g, start) || seen[start.0][start.1] {
+            continue;
+        }
+        let mut component = vec![];
+        let mut que = VecDeque::from([start]);
+        seen[start.0][start.1] = true;
+        while let Some(pos) = que.pop_front() {
+            component.push(pos);
+            for (_, d) in DIRECTIONS {
+                let (ni, nj) = Board::get_next(pos, d);
+                if is_floor(g, (ni, nj)) && !seen[ni][nj] {
+                    seen[ni][nj] = true;
+                    que.push_back((ni, nj));
+                }
+            }
+        }
+        res.push(component);
+    }
+    res
+}
+
+/// Shortest (by pushes) way of pushing a box from `from` to `to` while both box and player stay inside `area`,
+/// treating squares in `blocked` as walls. The player starts at `player`.
+fn route(
+    g: &Board,
+    area: &[Vec<bool>],
+    blocked: &[Vec<bool>],
+    player: (usize, usize),
+    from: (usize, usize),
+    to: (usize, usize),
+) -> Option<Vec<Segment>> {
+    let free = |(i, j): (usize, usize), box_pos: (usize, usize)| {
+        g.pos_is_valid(i, j) && area[i][j] && !blocked[i][j] && (i, j) != box_pos
+    };
+    let region = |player: (usize, usize), box_pos: (usize, usize)| {
+        let mut seen = vec![vec![false; g.m]; g.n];
+        let mut que = VecDeque::from([player]);
+        seen[player.0][player.1] = true;
+        while let Some(pos) = que.pop_front() {
+            for (_, d) in DIRECTIONS {
+                let (ni, nj) = Board::get_next(pos, d);
+                if free((ni, nj), box_pos) && !seen[ni][nj] {
+                    seen[ni][nj] = true;
+                    que.push_back((ni, nj));
+                }
+            }
+        }
+        seen
+    };
+    let norm = |seen: &[Vec<bool>]| {
+        seen.iter()
+            .enumerate()
+            .find_map(|(i, row)| row.iter().position(|v| *v).map(|j| (i, j)))
+            .unwrap()
+    };
+    // (box, normalized player) -> (previous state, box position before the push, direction)
+    type Key = ((usize, usize), (usize, usize));
+    type Push = (Key, (usize, usize), BoardCommand);
+    let mut parent: HashMap<Key, Option<Push>> = HashMap::new();
+    let start = region(player, from);
+    let start_key = (from, norm(&start));
+    parent.insert(start_key, None);
+    let mut que = VecDeque::from([(start_key, start)]);
+    while let Some((key @ (box_pos, _), seen)) = que.pop_front() {
+        if box_pos == to {
+            let mut pushes = vec![];
+            let mut cur = key;
+            while let Some(Some((prev, before, direction))) = parent.get(&cur) {
+                pushes.push((*before, *direction));
+                cur = *prev;
+            }
+            pushes.reverse();
+            let mut segments: Vec<Segment> = vec![];
+            for (before, direction) in pushes {
+                match segments.last_mut() {
+                    Some(last) if last.direction == direction => last.pushes += 1,
+                    _ => segments.push(Segment {
+                        box_pos: before,
+                        direction,
+                        pushes: 1,
+                    }),
+                }
+            }
+            return Some(segments);
+        }
+        for (direction, d) in DIRECTIONS {
+            let behind = Board::get_next(box_pos, (d.0.wrapping_neg(), d.1.wrapping_neg()));
+            let ahead = Board::get_next(box_pos, d);
+            if !(g.pos_is_valid(behind.0, behind.1)
+                && seen[behind.0][behind.1]
+                && free(ahead, box_pos))
+            {
+                continue;
+            }
+            let next_seen = region(box_pos, ahead);
+            let next_key = (ahead, norm(&next_seen));
+            if parent.contains_key(&next_key) {
+                continue;
+            }
+            parent.insert(next_key, Some((key, box_pos, direction)));
+            que.push_back((next_key, next_seen));
+        }
+    }
+    None
+}
+
+impl GoalRoom {
+    fn new(g: &Board, entrance: (usize, usize), squares: Vec<(usize, usize)>) -> Option<Self> {
+        let mut area = vec![vec![false; g.m]; g.n];
+        for &(i, j) in squares.iter() {
+            area[i][j] = true;
+        }
+        area[entrance.0][entrance.1] = true;
+        let first = DIRECTIONS
+            .into_iter()
+            .map(|(_, d)| Board::get_next(entrance, d))
+            .find(|&(i, j)| g.pos_is_valid(i, j) && area[i][j] && (i, j) != entrance)?;
+        let goals: Vec<_> = squares
+            .iter()
+            .copied()
+            .filter(|&(i, j)| matches!(g.cells[i][j].grid, Grid::Target))
+            .collect();
+        // fill greedily, deepest goal first, as long as every remaining goal stays reachable
+        let mut blocked = vec![vec![false; g.m]; g.n];
+        let mut fill_order = vec![];
+        let mut routes = vec![];
+        while fill_order.len() < goals.len() {
+            let mut next: Option<((usize, usize), Vec<Segment>)> = None;
+            for &goal in goals.iter() {
+                if blocked[goal.0][goal.1] {
+                    continue;
+                }
+                let Some(r) = route(g, &area, &blocked, entrance, first, goal) else {
+                    continue;
+                };
+                blocked[goal.0][goal.1] = true;
+                let ok = goals.iter().all(|&(i, j)| {
+                    blocked[i][j] || route(g, &area, &blocked, entrance, first, (i, j)).is_some()
+                });
+                blocked[goal.0][goal.1] = false;
+                let len = |r: &[Segment]| r.iter().map(|s| s.pushes as usize).sum::<usize>();
+                if ok && next.as_ref().is_none_or(|(_, best)| len(&r) > len(best)) {
+                    next = Some((goal, r));
+                }
+            }
+            let next = next?;
+            blocked[next.0 .0][next.0 .1] = true;
+            fill_order.push(next.0);
+            routes.push(next.1);
+        }
+        Some(Self {
+            entrance,
+            first,
+            squares,
+            fill_order,
+            routes,
+        })
+    }
+
+    /// Finds the goal rooms of `g`: areas cut off by a single floor square that contain goals but no boxes, while
+    /// the rest of the level holds every box.
+    pub fn calc_goal_rooms(g: &Board) -> Vec<Self> {
+        let floor: Vec<_> = (0..g.n)
+            .flat_map(|i| (0..g.m).map(move |j| (i, j)))
+            .filter(|&pos| is_floor(g, pos))
+            .collect();
+        let mut candidates = vec![];
+        for &entrance in floor.iter() {
+            if matches!(g.cells[entrance.0][entrance.1].grid, Grid::Target) {
+                continue;
+            }
+            let components = components_without(g, entrance);
+            if components.len() < 2 {
+                continue;
+            }
+            for component in components {
+                let has_goal = component
+                    .iter()
+                    .any(|&(i, j)| matches!(g.cells[i][j].grid, Grid::Target));
+                let has_box = component
+                    .iter()
+                    .any(|&(i, j)| matches!(g.cells[i][j].entity, Some(Entity::Box)));
+                let touches_entrance = DIRECTIONS
+                    .into_iter()
+                    .filter(|(_, d)| component.contains(&Board::get_next(entrance, *d)))
+                    .count();
+                if has_goal && !has_box && touches_entrance == 1 {
+                    candidates.push((entrance, component));
+                }
+            }
+        }
+        // prefer the smallest rooms, rooms must not overlap
+        candidates.sort_by_key(|(_, component)| component.len());
+        let mut taken = vec![vec![false; g.m]; g.n];
+        let mut res = vec![];
+        for (entrance, component) in candidates {
+            if component.iter().any(|&(i, j)| taken[i][j]) || taken[entrance.0][entrance.1] {
+                continue;
+            }
+            let goals_inside = component
+                .iter()
+                .filter(|&&(i, j)| matches!(g.cells[i][j].grid, Grid::Target))
+                .count();
+            if goals_inside > g.num_box {
+                continue;
+            }
+            if let Some(room) = Self::new(g, entrance, component) {
+                for &(i, j) in room.squares.iter() {
+                    taken[i][j] = true;
+                }
+                taken[entrance.0][entrance.1] = true;
+                res.push(room);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GoalRoom;
+    use crate::game::board::Board;
+
+    #[test]
+    fn test_goal_room_0() {
+        let g = Board::from(
+            "#########\n\
+             #.  #   #\n\
+             #.  ##$ #\n\
+             #..   $ #\n\
+             #.  ##$@#\n\
+             #.  ##$ #\n\
+             #   #$$ #\n\
+             #########",
+        );
+        let rooms = GoalRoom::calc_goal_rooms(&g);
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].entrance, (3, 4));
+        assert_eq!(rooms[0].first, (3, 3));
+        assert_eq!(rooms[0].fill_order.len(), 6);
+        // the goal next to the entrance has to be filled last
+        assert_eq!(rooms[0].fill_order.last(), Some(&(3, 2)));
+        assert_eq!(rooms[0].routes.last().unwrap().len(), 1);
+    }
+}
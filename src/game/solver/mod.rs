@@ -0,0 +1,1807 @@
+pub mod breakdown;
+mod cancel;
+mod checkpoint;
+mod corral;
+mod deadlock;
+mod enumerate;
+mod external;
+mod goal_room;
+pub mod optimizer;
+mod pareto;
+pub mod pattern_db;
+pub mod portfolio;
+mod proof;
+mod state_table;
+
+use super::board::Board;
+use super::board_command::BoardCommand;
+use super::entity::Entity;
+use super::grid::Grid;
+pub use cancel::CancellationToken;
+pub use checkpoint::Checkpointing;
+use checkpoint::Snapshot;
+use crossbeam_channel::Sender;
+use deadlock::DeadlockDetector;
+pub use deadlock::{DeadlockExplanation, DeadlockKind};
+pub use external::ExternalMemory;
+use external::Spill;
+use goal_room::{GoalRoom, Segment};
+pub use pareto::FrontPoint;
+pub use proof::{Certificate, Verdict};
+use state_table::StateTable;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Zobrist key of `entity` standing at the flattened board index `idx`. Keys are derived with splitmix64 rather
+/// than drawn from a random table, so every `DeltaBoard` agrees on them without sharing any state.
+#[inline]
+fn zobrist_key(idx: usize, entity: Entity) -> u64 {
+    let seed = (idx as u64) << 1 | matches!(entity, Entity::Box) as u64;
+    let mut z = seed.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const DIRECTIONS: [(BoardCommand, (usize, usize)); 4] = [
+    (BoardCommand::Up, (usize::MAX, 0)),
+    (BoardCommand::Down, (1, 0)),
+    (BoardCommand::Left, (0, usize::MAX)),
+    (BoardCommand::Right, (0, 1)),
+];
+
+#[inline]
+fn direction_delta(command: BoardCommand) -> (usize, usize) {
+    DIRECTIONS
+        .into_iter()
+        .find_map(|(c, d)| if c == command { Some(d) } else { None })
+        .unwrap_or((0, 0))
+}
+
+#[derive(Clone)]
+struct DeltaBoard<'a> {
+    g: &'a Board, // we only care about grids in it
+    boxes: Vec<(usize, usize)>,
+    occupancy: Vec<u64>, // bitset over `i * m + j`, set where a box stands
+    n: usize,
+    m: usize,
+    i: usize,
+    j: usize,
+    norm: usize,       // top-left-most square reachable by the player, as `i * m + j`
+    num_ok_box: usize, // number of boxes on targets
+    num_box: usize,
+    zobrist: u64, // xor of `zobrist_key` over all boxes, updated per moved box
+}
+
+impl PartialEq for DeltaBoard<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        // DeltaBoard would only be used as part of search state, thus we assume grids are always the same & skip
+        // redundant checks. The exact player square doesn't matter, only the region it could walk around in.
+        self.zobrist == other.zobrist
+            && self.norm == other.norm
+            && self.occupancy == other.occupancy
+    }
+}
+
+impl Eq for DeltaBoard<'_> {}
+
+impl Debug for DeltaBoard<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut ng = self.g.clone();
+        for row in ng.cells.iter_mut() {
+            for cell in row.iter_mut() {
+                cell.entity = None;
+            }
+        }
+        for (i, j) in self.boxes.iter() {
+            ng.cells[*i][*j].entity = Some(Entity::Box);
+        }
+        ng.cells[self.i][self.j].entity = Some(Entity::Player);
+        writeln!(f, "Board [")?;
+        for row in &ng.cells {
+            for cell in row {
+                write!(f, "{:?}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl<'a> From<&'a Board> for DeltaBoard<'a> {
+    fn from(value: &'a Board) -> Self {
+        let boxes = value
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(move |(j, cell)| (i, j, cell.entity))
+            })
+            .filter_map(|(i, j, entity)| match entity {
+                Some(Entity::Box) => Some((i, j)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut occupancy = vec![0; (value.n * value.m).div_ceil(64)];
+        let mut zobrist = 0;
+        for &(i, j) in boxes.iter() {
+            let idx = i * value.m + j;
+            occupancy[idx >> 6] |= 1 << (idx & 63);
+            zobrist ^= zobrist_key(idx, Entity::Box);
+        }
+        let mut res = Self {
+            g: value,
+            boxes,
+            occupancy,
+            n: value.n,
+            m: value.m,
+            i: value.i,
+            j: value.j,
+            norm: value.i * value.m + value.j,
+            num_ok_box: value.num_ok_box,
+            num_box: value.num_box,
+            zobrist,
+        };
+        res.normalize();
+        res
+    }
+}
+
+impl Hash for DeltaBoard<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.position_hash().hash(state);
+    }
+}
+
+impl DeltaBoard<'_> {
+    fn is_finished(&self) -> bool {
+        self.num_ok_box == self.num_box
+    }
+
+    /// Zobrist hash of the boxes and the normalized player square.
+    fn position_hash(&self) -> u64 {
+        self.zobrist ^ zobrist_key(self.norm, Entity::Player)
+    }
+
+    #[inline]
+    pub fn pos_is_valid(&self, i: usize, j: usize) -> bool {
+        self.g.pos_is_valid(i, j)
+    }
+
+    fn get_grid_at(&self, i: usize, j: usize) -> Grid {
+        // rows may be ragged, anything past the end of a row is outside the level
+        self.g.cells[i].get(j).map_or(Grid::Wall, |cell| cell.grid)
+    }
+
+    #[inline]
+    fn has_box_at(&self, i: usize, j: usize) -> bool {
+        let idx = i * self.m + j;
+        self.occupancy[idx >> 6] & (1 << (idx & 63)) != 0
+    }
+
+    #[inline]
+    fn is_free(&self, i: usize, j: usize) -> bool {
+        // floor that neither a wall nor a box is standing on, the player doesn't count
+        self.pos_is_valid(i, j)
+            && matches!(self.get_grid_at(i, j), Grid::Ground | Grid::Target)
+            && !self.has_box_at(i, j)
+    }
+
+    /// Flood fills the squares the player could walk to without pushing anything. For each reached square (indexed
+    /// by `i * m + j`) it stores the command that first stepped onto it together with the walking distance,
+    /// `BoardCommand::Null` for the start square.
+    fn reachable(&self) -> Vec<Option<(BoardCommand, usize)>> {
+        let mut res = vec![None; self.n * self.m];
+        let mut que = VecDeque::new();
+        res[self.i * self.m + self.j] = Some((BoardCommand::Null, 0));
+        que.push_back((self.i, self.j, 0));
+        while let Some((i, j, d)) = que.pop_front() {
+            for (command, delta) in DIRECTIONS {
+                let (ni, nj) = Board::get_next((i, j), delta);
+                if self.is_free(ni, nj) && res[ni * self.m + nj].is_none() {
+                    res[ni * self.m + nj] = Some((command, d + 1));
+                    que.push_back((ni, nj, d + 1));
+                }
+            }
+        }
+        res
+    }
+
+    /// Walks back along the flood fill from `reachable` to recover the shortest walk onto `pos`.
+    fn walk_to(
+        &self,
+        reachable: &[Option<(BoardCommand, usize)>],
+        pos: (usize, usize),
+    ) -> Vec<BoardCommand> {
+        let mut steps = vec![];
+        let mut pos = pos;
+        while let Some((step, _)) =
+            reachable[pos.0 * self.m + pos.1].filter(|(v, _)| *v != BoardCommand::Null)
+        {
+            steps.push(step);
+            let (di, dj) = direction_delta(step);
+            pos = Board::get_next(pos, (di.wrapping_neg(), dj.wrapping_neg()));
+        }
+        steps.reverse();
+        steps
+    }
+
+    fn normalize(&mut self) {
+        self.norm = self
+            .reachable()
+            .iter()
+            .position(|v| v.is_some())
+            .unwrap_or(self.i * self.m + self.j);
+    }
+
+    /// A full board with the boxes and the player of this one, for showing it to somebody.
+    fn to_board(&self) -> Board {
+        let mut res = self.g.clone();
+        for row in res.cells.iter_mut() {
+            for cell in row.iter_mut() {
+                cell.entity = None;
+            }
+        }
+        for &(i, j) in self.boxes.iter() {
+            res.cells[i][j].entity = Some(Entity::Box);
+        }
+        res.cells[self.i][self.j].entity = Some(Entity::Player);
+        (res.i, res.j) = (self.i, self.j);
+        res.num_ok_box = self.num_ok_box;
+        res
+    }
+
+    /// The same level with the boxes at `boxes` and the player at `player`.
+    fn with_boxes(&self, boxes: Vec<(usize, usize)>, player: (usize, usize)) -> Self {
+        let mut res = self.clone();
+        res.boxes = boxes;
+        (res.i, res.j) = player;
+        res.retain_boxes(|_| true)
+    }
+
+    /// A copy of this board holding only the boxes `keep` agrees on.
+    fn retain_boxes(&self, keep: impl Fn((usize, usize)) -> bool) -> Self {
+        let mut res = self.clone();
+        res.boxes.retain(|&pos| keep(pos));
+        res.occupancy.fill(0);
+        res.zobrist = 0;
+        res.num_ok_box = 0;
+        for &(i, j) in res.boxes.iter() {
+            let idx = i * res.m + j;
+            res.occupancy[idx >> 6] |= 1 << (idx & 63);
+            res.zobrist ^= zobrist_key(idx, Entity::Box);
+            if let Grid::Target = res.get_grid_at(i, j) {
+                res.num_ok_box += 1;
+            }
+        }
+        res.num_box = res.boxes.len();
+        res.normalize();
+        res
+    }
+
+    fn move_box(&mut self, src: (usize, usize), dst: (usize, usize)) {
+        // keeps `boxes`, the occupancy bitset and the zobrist hash in sync
+        for pos in self.boxes.iter_mut() {
+            if *pos == src {
+                *pos = dst;
+                break;
+            }
+        }
+        let (src_idx, dst_idx) = (src.0 * self.m + src.1, dst.0 * self.m + dst.1);
+        self.occupancy[src_idx >> 6] &= !(1 << (src_idx & 63));
+        self.occupancy[dst_idx >> 6] |= 1 << (dst_idx & 63);
+        self.zobrist ^= zobrist_key(src_idx, Entity::Box) ^ zobrist_key(dst_idx, Entity::Box);
+    }
+
+    fn push_entity(
+        &mut self,
+        src: (usize, usize),
+        d: (usize, usize),
+    ) -> (Option<(usize, usize)>, bool) {
+        // returns Some(coord) if any block has been pushed
+        let (ni, nj) = Board::get_next(src, d);
+        let mut new_box_pos = None;
+        let mut player_moved = false;
+        let is_valid = |i: usize, j: usize| i < self.n && j < self.m;
+        if is_valid(ni, nj) && matches!(self.get_grid_at(ni, nj), Grid::Ground | Grid::Target) {
+            // if there's a box, try to push it first
+            if self.has_box_at(ni, nj) {
+                let (nni, nnj) = Board::get_next((ni, nj), d);
+                if self.is_free(nni, nnj) {
+                    new_box_pos = Some((nni, nnj));
+                    self.move_box((ni, nj), (nni, nnj));
+
+                    self.num_ok_box = self
+                        .num_ok_box
+                        .overflowing_add(
+                            match (self.get_grid_at(ni, nj), self.get_grid_at(nni, nnj)) {
+                                (Grid::Ground, Grid::Target) => 1,
+                                (Grid::Target, Grid::Ground) => usize::MAX,
+                                _ => 0,
+                            },
+                        )
+                        .0;
+                }
+            }
+            if !self.has_box_at(ni, nj) {
+                player_moved = true;
+                self.i = ni;
+                self.j = nj;
+            }
+        }
+        if new_box_pos.is_some() {
+            // walking never changes the reachable region, pushing might
+            self.normalize();
+        }
+        (new_box_pos, player_moved)
+    }
+
+    fn execute(&mut self, command: BoardCommand) -> (Option<(usize, usize)>, bool) {
+        match command {
+            BoardCommand::Up => self.push_entity((self.i, self.j), (usize::MAX, 0)),
+            BoardCommand::Down => self.push_entity((self.i, self.j), (1, 0)),
+            BoardCommand::Left => self.push_entity((self.i, self.j), (0, usize::MAX)),
+            BoardCommand::Right => self.push_entity((self.i, self.j), (0, 1)),
+            _ => (None, false),
+        }
+    }
+}
+
+/// Which player square goes into a visited-set key, see `Solver::encode`. Two ways into the same boxes can cost
+/// different walks afterwards, so only the exact square keeps the better one when moves are counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Keying {
+    Region, // the normalized square, standing for everywhere the player can walk to
+    Exact,  // the square the player stands on
+}
+
+/// One node of the search tree. Rather than every state carrying its whole move history, a node only remembers its
+/// parent and the push that led there; the walks in between are recovered once a solution has been found.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Node {
+    parent: u32,
+    box_pos: u32, // position of the box before the first push, as `i * m + j`
+    direction: BoardCommand,
+    pushes: u16, // how many times the box has been pushed along `direction`
+}
+
+impl Node {
+    const ROOT: u32 = u32::MAX;
+}
+
+#[derive(PartialEq, Eq)]
+struct State<'a> {
+    g: DeltaBoard<'a>,
+    node: Node,
+    cost: usize,     // number of moves from the initial board
+    est_rest: usize, // A*, we use summed L1 distance to nearest goal to estimate the lowerbound
+    priority: usize, // what the open list is ordered by, see `SearchStrategy`
+    seq: u64, // order of insertion, breaks ties so that the search doesn't depend on the heap's layout
+}
+
+impl PartialOrd for State<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State<'_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // among equally good states the one closer to the goal goes first, then the oldest
+        self.priority
+            .cmp(&other.priority)
+            .then(self.est_rest.cmp(&other.est_rest))
+            .then(self.seq.cmp(&other.seq))
+    }
+}
+
+/// Budgets after which the solver gives up, `None` meaning unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SolverLimits {
+    pub max_states: Option<usize>, // number of expanded states
+    pub timeout: Option<Duration>,
+    pub max_memory: Option<usize>, // approximate bytes held by the search
+}
+
+impl SolverLimits {
+    /// The first limit a search that got this far has run into, if any.
+    fn exceeded(&self, expanded: usize, elapsed: Duration, memory: usize) -> Option<Limit> {
+        if self.max_states.is_some_and(|v| expanded >= v) {
+            Some(Limit::States)
+        } else if self.timeout.is_some_and(|v| elapsed >= v) {
+            Some(Limit::Time)
+        } else if self.max_memory.is_some_and(|v| memory >= v) {
+            Some(Limit::Memory)
+        } else {
+            None
+        }
+    }
+}
+
+/// How the open list is ordered, trading solution quality for speed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchStrategy {
+    AStar,           // f = g + h, finds a solution with the fewest moves
+    Weighted(usize), // f = g + w * h with `w` given in percent, the solution is at most `w` times longer than optimal
+    Greedy,          // f = h, only looks at how far the boxes are from the goals
+}
+
+impl SearchStrategy {
+    fn priority(self, cost: usize, est_rest: usize) -> usize {
+        match self {
+            Self::AStar => cost + est_rest,
+            Self::Weighted(percent) => cost * 100 + est_rest * percent,
+            Self::Greedy => est_rest,
+        }
+    }
+
+    /// Whether the first solution found is guaranteed to be a shortest one.
+    pub fn is_optimal(self) -> bool {
+        match self {
+            Self::AStar => true,
+            Self::Weighted(percent) => percent <= 100,
+            Self::Greedy => false,
+        }
+    }
+}
+
+impl std::fmt::Display for SearchStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AStar => write!(f, "A*"),
+            Self::Weighted(percent) => {
+                write!(f, "weighted A* (w = {:.1})", *percent as f64 / 100.0)
+            }
+            Self::Greedy => write!(f, "greedy"),
+        }
+    }
+}
+
+/// Which of the solver's pruning techniques are switched on, and how long it may search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolverConfig {
+    pub deadlocks: Vec<DeadlockKind>, // run after every push in this order, put the cheap ones first
+    pub pi_corrals: bool, // only expand the pushes into a PI-corral, can cost extra moves
+    pub tunnels: bool,
+    pub goal_rooms: bool,
+    pub pattern_db: Option<PathBuf>, // file backing `DeadlockKind::Patterns`, patterns are only kept in memory if `None`
+    pub limits: SolverLimits,
+    pub strategy: SearchStrategy,
+    pub checkpoint: Option<Checkpointing>, // resume from and keep saving to a file, only used by `Solver::solve`
+    pub external: Option<ExternalMemory>, // spill to disk instead of running out of memory, never checkpointed
+}
+
+impl SolverConfig {
+    /// Whether a search with this config leaves out nothing but boards that are dead and pushes a tunnel forces
+    /// anyway, so that finding no solution means there is none. PI-corrals and goal room macros skip pushes. A pattern
+    /// file is read from disk without being checked, so nothing guarantees the patterns in it are really deadlocks.
+    pub fn is_exhaustive(&self) -> bool {
+        !self.pi_corrals && !self.goal_rooms && self.pattern_db.is_none()
+    }
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            deadlocks: vec![DeadlockKind::DeadSquares, DeadlockKind::Freeze],
+            // the pushes into a PI-corral are all that is needed to find the fewest pushes, but the walks around the
+            // corral may come out longer than they had to be
+            pi_corrals: false,
+            tunnels: true,
+            goal_rooms: true,
+            pattern_db: None,
+            limits: SolverLimits::default(),
+            strategy: SearchStrategy::AStar,
+            checkpoint: None,
+            external: None,
+        }
+    }
+}
+
+impl std::fmt::Display for SolverConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let deadlocks = self
+            .deadlocks
+            .iter()
+            .map(|kind| kind.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match deadlocks.is_empty() {
+            true => write!(f, "{} without deadlock checks", self.strategy),
+            false => write!(f, "{} with {}", self.strategy, deadlocks),
+        }
+    }
+}
+
+/// How far a search got, reported when it has to give up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchStats {
+    pub expanded: usize,
+    pub frontier: usize, // states waiting in the open list
+    pub best_h: usize,   // lowest estimate of remaining moves among expanded states
+    pub elapsed: Duration,
+}
+
+/// A snapshot of a running search, sent every so often to whoever asked for it with `Solver::with_progress`.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub stats: SearchStats,
+    pub f_bound: usize, // moves so far plus estimate of the state being expanded, never goes down in A*
+    pub states_per_sec: f64, // since the previous snapshot
+    pub board: Board,   // the state being expanded, one of the most promising ones
+}
+
+/// The budget from `SolverLimits` a search ran out of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    States,
+    Time,
+    Memory,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::States => write!(f, "too many states"),
+            Self::Time => write!(f, "out of time"),
+            Self::Memory => write!(f, "out of memory"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolverError {
+    Unsolvable, // no solution found by this search, which proves there is none with goal rooms off
+    UnreachableBox { pos: (usize, usize) },
+    Cancelled,
+    LimitExceeded { limit: Limit, stats: SearchStats },
+    Io(String), // reading or writing the parts of the search kept on disk failed
+}
+
+impl std::fmt::Display for SolverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsolvable => write!(f, "No solution"),
+            Self::UnreachableBox { pos: (i, j) } => write!(
+                f,
+                "The box at row {}, column {} could never reach any goal",
+                i + 1,
+                j + 1
+            ),
+            Self::Cancelled => write!(f, "Cancelled"),
+            Self::LimitExceeded { limit, stats } => write!(
+                f,
+                "Gave up, {}: expanded {} states in {:.1?}, {} in frontier, best h {}",
+                limit, stats.expanded, stats.elapsed, stats.frontier, stats.best_h
+            ),
+            Self::Io(e) => write!(f, "Disk error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
+impl From<std::io::Error> for SolverError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub seq: Vec<BoardCommand>,
+    pub visited_states: usize,
+    pub pruned: Vec<(DeadlockKind, usize)>, // how many boards each detector threw away
+    pub optimal: bool,                      // proven to need the fewest moves possible
+}
+
+pub struct Solver<'a> {
+    board: &'a Board,
+    config: SolverConfig,
+    min_dist_to_goal: Vec<Vec<Option<usize>>>,
+    insolvable: Vec<Vec<bool>>,
+    floor_index: Vec<u16>, // `i * m + j` to the index among floor squares, `u16::MAX` for walls
+    goal_rooms: Vec<GoalRoom>,
+    detectors: Vec<Box<dyn DeadlockDetector>>,
+    progress: Option<(Sender<Progress>, Duration)>,
+}
+
+impl<'a> Solver<'a> {
+    fn calc_min_dist_to_goal(g: &Board) -> Vec<Vec<Option<usize>>> {
+        // returns an array the same size as the game board, where at i, j, it stores the L1 distance to the
+        // nearest goal
+        let mut que = VecDeque::new();
+        let mut res = vec![vec![None; g.m]; g.n];
+        for (i, row) in g.cells.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if let Grid::Target = cell.grid {
+                    que.push_back(((i, j), 0));
+                }
+            }
+        }
+        while let Some(((i, j), d)) = que.pop_front() {
+            if res[i][j].is_some() {
+                // updated by previous visits
+                continue;
+            }
+            res[i][j] = Some(d);
+            for (di, dj) in [(1, 0), (0, 1), (usize::MAX, 0), (0, usize::MAX)] {
+                let (ni, nj) = Board::get_next((i, j), (di, dj));
+                if g.pos_is_valid(ni, nj)
+                    && res[ni][nj].is_none()
+                    && matches!(g.cells[ni][nj].grid, Grid::Ground | Grid::Target)
+                {
+                    que.push_back(((ni, nj), d + 1));
+                }
+            }
+        }
+        res
+    }
+
+    fn calc_insolvable(g: &Board) -> Vec<Vec<bool>> {
+        let mut visited = vec![vec![false; g.m]; g.n];
+        // start pulling a virtual box at all target positions
+        for (si, sj) in g
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, cell)| (i, j, cell)))
+            .filter_map(|(i, j, cell)| {
+                if let Grid::Target = cell.grid {
+                    Some((i, j))
+                } else {
+                    None
+                }
+            })
+        {
+            let mut que = VecDeque::new();
+            que.push_back((si, sj));
+            while let Some((i, j)) = que.pop_front() {
+                if visited[i][j] {
+                    continue;
+                }
+                visited[i][j] = true;
+                for (di, dj) in [(usize::MAX, 0), (1, 0), (0, usize::MAX), (0, 1)] {
+                    // next position of box
+                    let (ni, nj) = Board::get_next((i, j), (di, dj));
+                    if g.pos_is_valid(ni, nj)
+                        && matches!(g.cells[ni][nj].grid, Grid::Ground | Grid::Target)
+                        && !visited[ni][nj]
+                    {
+                        // next position of puller
+                        let (nni, nnj) = Board::get_next((ni, nj), (di, dj));
+                        if g.pos_is_valid(nni, nnj)
+                            && matches!(g.cells[nni][nnj].grid, Grid::Ground | Grid::Target)
+                        {
+                            que.push_back((ni, nj))
+                        }
+                    }
+                }
+            }
+        }
+
+        visited
+            .into_iter()
+            .map(|v| v.into_iter().map(|b| !b).collect())
+            .collect()
+    }
+
+    fn calc_floor_index(g: &Board) -> Vec<u16> {
+        // rows may be ragged, only the first `m` cells of each row take part in the search
+        let mut res = vec![u16::MAX; g.n * g.m];
+        let mut next = 0;
+        for (i, row) in g.cells.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate().take(g.m) {
+                if matches!(cell.grid, Grid::Ground | Grid::Target) {
+                    res[i * g.m + j] = next;
+                    next += 1;
+                }
+            }
+        }
+        res
+    }
+
+    pub fn new(g: &'a Board) -> Self {
+        Self::with_config(g, SolverConfig::default())
+    }
+
+    pub fn with_config(g: &'a Board, config: SolverConfig) -> Self {
+        Self {
+            board: g,
+            min_dist_to_goal: Self::calc_min_dist_to_goal(g),
+            insolvable: Self::calc_insolvable(g),
+            floor_index: Self::calc_floor_index(g),
+            goal_rooms: match config.goal_rooms {
+                true => GoalRoom::calc_goal_rooms(g),
+                false => vec![],
+            },
+            detectors: config
+                .deadlocks
+                .iter()
+                .map(|&kind| deadlock::create_detector(kind, g, &config))
+                .collect(),
+            config,
+            progress: None,
+        }
+    }
+
+    /// Makes the search send a `Progress` through `sender` at most once per `interval`.
+    pub fn with_progress(mut self, sender: Sender<Progress>, interval: Duration) -> Self {
+        self.progress = Some((sender, interval));
+        self
+    }
+
+    /// Writes the compact visited-set key of `board` into `key`: the floor indices of all boxes in ascending order,
+    /// followed by the floor index of the player square `keying` asks for. Everything that writes or looks up keys of
+    /// one visited set has to go through here with the same `keying`.
+    fn encode(&self, board: &DeltaBoard<'_>, keying: Keying, key: &mut Vec<u16>) {
+        key.clear();
+        for (w, &word) in board.occupancy.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                key.push(self.floor_index[(w << 6) | word.trailing_zeros() as usize]);
+                word &= word - 1;
+            }
+        }
+        key.push(match keying {
+            Keying::Region => self.floor_index[board.norm],
+            Keying::Exact => self.floor_index[board.i * board.m + board.j],
+        });
+    }
+
+    fn calc_est_rest(&self, board: &DeltaBoard<'_>) -> Result<usize, (usize, usize)> {
+        // fails with the position of a box that can't reach any goal
+        let mut sum = 0;
+        for &(i, j) in board.boxes.iter() {
+            match self.min_dist_to_goal[i][j] {
+                Some(v) => sum += v,
+                None => return Err((i, j)),
+            }
+        }
+        Ok(sum)
+    }
+
+    fn get_next_pushes(g: &DeltaBoard<'a>) -> Vec<(DeltaBoard<'a>, usize, BoardCommand)> {
+        Self::get_next_pushes_from(g, &g.reachable())
+    }
+
+    fn get_next_pushes_from(
+        g: &DeltaBoard<'a>,
+        reachable: &[Option<(BoardCommand, usize)>],
+    ) -> Vec<(DeltaBoard<'a>, usize, BoardCommand)> {
+        // figure out all possible one push next steps, i.e. closure of walk around
+        // returns (State with the player standing next to the box, length of the walk to get there, command to
+        // push one box along one direction)
+        let mut res = vec![];
+        for (idx, walk_len) in reachable
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, v)| v.map(|(_, d)| (idx, d)))
+        {
+            let (i, j) = (idx / g.m, idx % g.m);
+            for (command, d) in DIRECTIONS {
+                let (ni, nj) = Board::get_next((i, j), d);
+                if !(g.pos_is_valid(ni, nj) && g.has_box_at(ni, nj)) {
+                    continue;
+                }
+                let (nni, nnj) = Board::get_next((ni, nj), d);
+                if !g.is_free(nni, nnj) {
+                    continue;
+                }
+                let mut h = g.clone();
+                h.i = i;
+                h.j = j;
+                res.push((h, walk_len, command));
+            }
+        }
+        res
+    }
+
+    fn is_wall(g: &DeltaBoard<'_>, (i, j): (usize, usize)) -> bool {
+        !g.pos_is_valid(i, j) || matches!(g.get_grid_at(i, j), Grid::Wall)
+    }
+
+    /// Whether the box just pushed onto `box_pos` is stuck in a one-wide tunnel together with the player behind it, in
+    /// which case pushing it further along `d` is the only thing worth doing and the state in between is skipped.
+    fn in_tunnel(&self, g: &DeltaBoard<'_>, box_pos: (usize, usize), d: (usize, usize)) -> bool {
+        let (ni, nj) = Board::get_next(box_pos, d);
+        if matches!(g.get_grid_at(box_pos.0, box_pos.1), Grid::Target)
+            || !g.is_free(ni, nj)
+            || self.insolvable[ni][nj]
+        {
+            return false;
+        }
+        [box_pos, (g.i, g.j)].into_iter().all(|pos| {
+            Self::is_wall(g, Board::get_next(pos, (d.1, d.0)))
+                && Self::is_wall(
+                    g,
+                    Board::get_next(pos, (d.1.wrapping_neg(), d.0.wrapping_neg())),
+                )
+        })
+    }
+
+    /// Carries a box that has just been pushed into a goal room on to the room's next goal in fill order. Returns the
+    /// resulting board, the moves spent on the way and the straight runs pushed, or `None` if the room isn't being
+    /// filled in order.
+    fn goal_room_macro(
+        &self,
+        g: &DeltaBoard<'a>,
+        box_pos: (usize, usize),
+    ) -> Option<(DeltaBoard<'a>, usize, &[Segment])> {
+        let room = self
+            .goal_rooms
+            .iter()
+            .find(|room| room.first == box_pos && room.entrance == (g.i, g.j))?;
+        let filled = room
+            .squares
+            .iter()
+            .filter(|&&(i, j)| (i, j) != box_pos && g.has_box_at(i, j))
+            .count();
+        if filled >= room.fill_order.len()
+            || !room.fill_order[..filled]
+                .iter()
+                .all(|&(i, j)| g.has_box_at(i, j))
+            || room.routes[filled].is_empty()
+        {
+            return None;
+        }
+        let mut h = g.clone();
+        let mut moves = 0;
+        for segment in room.routes[filled].iter() {
+            let (di, dj) = direction_delta(segment.direction);
+            let (bi, bj) = Board::get_next(segment.box_pos, (di.wrapping_neg(), dj.wrapping_neg()));
+            let (_, walk_len) = h.reachable()[bi * h.m + bj]?;
+            (h.i, h.j) = (bi, bj);
+            for _ in 0..segment.pushes {
+                if !h.execute(segment.direction).1 {
+                    return None;
+                }
+            }
+            moves += walk_len + segment.pushes as usize;
+        }
+        Some((h, moves, &room.routes[filled]))
+    }
+
+    fn rebuild_steps(&self, arena: &[Node], leaf: usize) -> Vec<BoardCommand> {
+        // replay the pushes from the root, filling in the shortest walks between them
+        let mut chain = vec![];
+        let mut cur = leaf as u32;
+        while cur != Node::ROOT {
+            chain.push(arena[cur as usize]);
+            cur = arena[cur as usize].parent;
+        }
+        let mut h = DeltaBoard::from(self.board);
+        let mut steps = vec![];
+        for node in chain.into_iter().rev().filter(|node| node.pushes > 0) {
+            let box_pos = (node.box_pos as usize / h.m, node.box_pos as usize % h.m);
+            let (di, dj) = direction_delta(node.direction);
+            let behind = Board::get_next(box_pos, (di.wrapping_neg(), dj.wrapping_neg()));
+            steps.append(&mut h.walk_to(&h.reachable(), behind));
+            (h.i, h.j) = behind;
+            for _ in 0..node.pushes {
+                h.execute(node.direction);
+                steps.push(node.direction);
+            }
+        }
+        steps
+    }
+
+    /// Searches for a solution until one is found, the search space runs out or `cancel` is cancelled.
+    pub fn solve(&self, cancel: &CancellationToken) -> Result<Solution, SolverError> {
+        let res = self.search(
+            cancel,
+            self.config.strategy,
+            usize::MAX,
+            self.config.checkpoint.as_ref(),
+            None,
+        );
+        for detector in self.detectors.iter() {
+            detector.finish();
+        }
+        res
+    }
+
+    /// Finds some solution quickly with greedy search, then searches again with weighted A*, only for solutions
+    /// shorter than the best one so far, and finally with A* for the optimal one. Every improvement is sent through
+    /// `improvements`. If the A* round finds nothing shorter, the best one is sent again with `optimal` set, unless
+    /// PI-corrals or goal room macros were in the way. Limits apply to each round on its own. Returns the best
+    /// solution found, or the error if there is none at all.
+    pub fn solve_anytime(
+        &self,
+        cancel: &CancellationToken,
+        improvements: &Sender<Solution>,
+    ) -> Result<Solution, SolverError> {
+        // a round that finds nothing shorter has to go through as many states as the proof itself, so only try one
+        // weight in between
+        let rounds = [
+            SearchStrategy::Greedy,
+            SearchStrategy::Weighted(300),
+            SearchStrategy::AStar,
+        ];
+        let mut best: Option<Solution> = None;
+        let mut res = Err(SolverError::Unsolvable);
+        for strategy in rounds {
+            let bound = best.as_ref().map_or(usize::MAX, |v| v.seq.len());
+            match self.search(cancel, strategy, bound, None, None) {
+                Ok(mut solution) => {
+                    if let Some(prev) = best.take() {
+                        if prev.seq.len() <= solution.seq.len() {
+                            solution = Solution {
+                                optimal: solution.optimal,
+                                ..prev
+                            };
+                        }
+                    }
+                    let _ = improvements.send(solution.clone());
+                    let optimal = solution.optimal;
+                    best = Some(solution);
+                    if optimal {
+                        break;
+                    }
+                }
+                // nothing shorter than the bound, which settles it if nothing was pruned on the way
+                Err(SolverError::Unsolvable) if best.is_some() => {
+                    if self.is_move_optimal(strategy) {
+                        if let Some(solution) = best.as_mut() {
+                            solution.optimal = true;
+                            let _ = improvements.send(solution.clone());
+                        }
+                    }
+                }
+                Err(e) => {
+                    res = Err(e);
+                    // an unreachable box or a finished search space won't change with another strategy
+                    if !matches!(res, Err(SolverError::LimitExceeded { .. })) {
+                        break;
+                    }
+                }
+            }
+        }
+        for detector in self.detectors.iter() {
+            detector.finish();
+        }
+        match best {
+            Some(solution) => Ok(solution),
+            None => res,
+        }
+    }
+
+    /// Whether `search` with `strategy` only comes back with solutions that take the fewest moves. Corral pruning
+    /// and goal room macros skip over pushes that can make for fewer moves.
+    fn is_move_optimal(&self, strategy: SearchStrategy) -> bool {
+        strategy.is_optimal() && !self.config.pi_corrals && self.goal_rooms.is_empty()
+    }
+
+    /// Identifies the level and everything about the search that changes its course, so that a checkpoint is only
+    /// resumed by the same search.
+    fn fingerprint(&self, strategy: SearchStrategy, bound: usize, keying: Keying) -> u64 {
+        let config = SolverConfig {
+            limits: SolverLimits::default(),
+            checkpoint: None,
+            ..self.config.clone()
+        };
+        format!(
+            "{:?}{:?}{:?}{}{:?}",
+            self.board.cells, config, strategy, bound, keying
+        )
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |h, v| {
+            (h ^ v as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    }
+
+    /// Rough size of a queued state, its board included.
+    fn state_bytes(&self) -> usize {
+        std::mem::size_of::<State>()
+            + self.board.num_box * std::mem::size_of::<(usize, usize)>()
+            + (self.board.n * self.board.m).div_ceil(64) * std::mem::size_of::<u64>()
+    }
+
+    /// Best-first search ordered by `strategy`, skipping every state that can't lead to a solution shorter than
+    /// `bound` moves. If it runs out of states, what it went through is written into `certificate`.
+    fn search(
+        &self,
+        cancel: &CancellationToken,
+        strategy: SearchStrategy,
+        bound: usize,
+        checkpoint: Option<&Checkpointing>,
+        certificate: Option<&mut Certificate>,
+    ) -> Result<Solution, SolverError> {
+        // basically A*
+        let start = Instant::now();
+        let limits = self.config.limits;
+        // when and at how many expanded states the last progress report went out
+        let mut last_report = (start, 0);
+        let state_bytes = self.state_bytes();
+        let init_delta_board = self.board.into();
+        let res_est_rest = self.calc_est_rest(&init_delta_board);
+        if let Err(pos) = res_est_rest {
+            return Err(SolverError::UnreachableBox { pos });
+        }
+        // where exactly the player stands changes how far it is to the next push, and so which of two ways into the
+        // same boxes is shorter. A proof only needs the boxes and the region.
+        let keying = match strategy.is_optimal() && certificate.is_none() {
+            true => Keying::Exact,
+            false => Keying::Region,
+        };
+        // a checkpoint can't capture what went to disk
+        let checkpoint = checkpoint.filter(|_| self.config.external.is_none());
+        let mut spill = match &self.config.external {
+            Some(external) => Some(Spill::new(self, external, state_bytes, keying)?),
+            None => None,
+        };
+        let fingerprint = self.fingerprint(strategy, bound, keying);
+        let resumed = checkpoint.and_then(|checkpoint| {
+            // a broken checkpoint is no reason to fail, the search just starts over
+            checkpoint::load(
+                &checkpoint.path,
+                fingerprint,
+                self.board,
+                self.detectors.len(),
+            )
+            .ok()
+            .flatten()
+        });
+        let mut snapshot = match resumed {
+            Some(snapshot) => snapshot,
+            None => Snapshot {
+                que: BinaryHeap::from([Reverse(State {
+                    g: init_delta_board,
+                    node: Node {
+                        parent: Node::ROOT,
+                        box_pos: 0,
+                        direction: BoardCommand::Null,
+                        pushes: 0,
+                    },
+                    cost: 0,
+                    est_rest: res_est_rest.unwrap(),
+                    priority: strategy.priority(0, res_est_rest.unwrap()),
+                    seq: 0,
+                })]),
+                visited: StateTable::new(self.board.num_box + 1),
+                arena: vec![],
+                pruned: vec![0; self.detectors.len()],
+                best_h: usize::MAX,
+                next_seq: 1,
+            },
+        };
+        let mut last_checkpoint = start;
+        // limits are about this run, whatever an earlier one did already
+        let resumed_states = snapshot.visited.len();
+        let mut key = Vec::with_capacity(self.board.num_box + 1);
+        let optimal = keying == Keying::Exact && self.is_move_optimal(strategy);
+        loop {
+            if let Some(spill) = spill.as_mut() {
+                spill.balance(&mut snapshot.visited, &mut snapshot.que)?;
+            }
+            let (expanded_on_disk, queued_on_disk) =
+                spill.as_ref().map_or((0, 0), |v| (v.expanded, v.queued));
+            let Snapshot {
+                que,
+                visited,
+                arena,
+                best_h,
+                ..
+            } = &snapshot;
+            // stop in between two states, where a checkpoint catches everything
+            let mut interrupted = cancel.is_cancelled().then_some(SolverError::Cancelled);
+            if interrupted.is_none() && limits != SolverLimits::default() {
+                let stats = SearchStats {
+                    expanded: visited.len() + expanded_on_disk,
+                    frontier: que.len() + queued_on_disk,
+                    best_h: *best_h,
+                    elapsed: start.elapsed(),
+                };
+                let memory = visited.memory_usage()
+                    + arena.capacity() * std::mem::size_of::<Node>()
+                    + que.capacity() * state_bytes;
+                let limit = limits.exceeded(stats.expanded - resumed_states, stats.elapsed, memory);
+                interrupted = limit.map(|limit| SolverError::LimitExceeded { limit, stats });
+            }
+            if let Some(checkpoint) = checkpoint {
+                if interrupted.is_some() || last_checkpoint.elapsed() >= checkpoint.interval {
+                    let _ = checkpoint::save(&checkpoint.path, fingerprint, &snapshot);
+                    last_checkpoint = Instant::now();
+                }
+            }
+            if let Some(e) = interrupted {
+                return Err(e);
+            }
+            let Snapshot {
+                que,
+                visited,
+                arena,
+                pruned,
+                best_h,
+                next_seq,
+            } = &mut snapshot;
+            let next = match spill.as_mut() {
+                Some(spill) => spill.pop(que)?,
+                None => que.pop().map(|Reverse(state)| state),
+            };
+            let Some(State {
+                g: h,
+                node,
+                cost,
+                est_rest,
+                ..
+            }) = next
+            else {
+                break;
+            };
+            if h.is_finished() {
+                arena.push(node);
+                if let Some(checkpoint) = checkpoint {
+                    let _ = fs::remove_file(&checkpoint.path);
+                }
+                return Ok(Solution {
+                    seq: self.rebuild_steps(arena, arena.len() - 1),
+                    visited_states: visited.len() + expanded_on_disk,
+                    pruned: self
+                        .detectors
+                        .iter()
+                        .map(|detector| detector.kind())
+                        .zip(pruned.iter().copied())
+                        .collect(),
+                    optimal,
+                });
+            }
+            self.encode(&h, keying, &mut key);
+            if !visited.insert(&key).1 {
+                continue;
+            }
+            *best_h = (*best_h).min(est_rest);
+            if let Some((sender, interval)) = &self.progress {
+                let now = Instant::now();
+                let since = now - last_report.0;
+                if since >= *interval {
+                    let _ = sender.send(Progress {
+                        stats: SearchStats {
+                            expanded: visited.len() + expanded_on_disk,
+                            frontier: que.len() + queued_on_disk,
+                            best_h: *best_h,
+                            elapsed: now - start,
+                        },
+                        f_bound: cost + est_rest,
+                        states_per_sec: (visited.len() + expanded_on_disk - last_report.1) as f64
+                            / since.as_secs_f64(),
+                        board: h.to_board(),
+                    });
+                    last_report = (now, visited.len() + expanded_on_disk);
+                }
+            }
+            let reachable = h.reachable();
+            let mut next_pushes = Self::get_next_pushes_from(&h, &reachable);
+            if let Some(corral) = self
+                .config
+                .pi_corrals
+                .then(|| self.find_pi_corral(&h, &reachable))
+                .flatten()
+            {
+                // some push into the corral has to happen anyway, so these are the only ones worth trying
+                next_pushes.retain(|(new_h, _, direction)| {
+                    let (bi, bj) = Board::get_next((new_h.i, new_h.j), direction_delta(*direction));
+                    corral.boxes.contains(&(bi, bj))
+                });
+            }
+            arena.push(node);
+            let parent = (arena.len() - 1) as u32;
+
+            for (mut new_h, walk_len, direction) in next_pushes {
+                let (bi, bj) = Board::get_next((new_h.i, new_h.j), direction_delta(direction));
+                let mut new_node = Node {
+                    parent,
+                    box_pos: (bi * new_h.m + bj) as u32,
+                    direction,
+                    pushes: 0,
+                };
+                loop {
+                    let (new_box_pos, player_moved) = new_h.execute(direction);
+                    new_node.pushes += 1;
+                    if !player_moved {
+                        // we can't push anymore
+                        break;
+                    }
+                    if let Some(box_pos) = new_box_pos {
+                        // the first detector to call it dead gets the count, and this line of pushes ends here
+                        if let Some(k) = self
+                            .detectors
+                            .iter()
+                            .position(|detector| detector.is_deadlock(self, &new_h, box_pos))
+                        {
+                            pruned[k] += 1;
+                            break;
+                        }
+                        if self.config.tunnels
+                            && self.in_tunnel(&new_h, box_pos, direction_delta(direction))
+                        {
+                            continue;
+                        }
+                        if let Some((macro_h, moves, segments)) =
+                            self.goal_room_macro(&new_h, box_pos)
+                        {
+                            self.encode(&macro_h, keying, &mut key);
+                            if !visited.contains(&key) {
+                                arena.push(new_node);
+                                let mut last = Node {
+                                    parent: Node::ROOT,
+                                    ..new_node
+                                };
+                                for (k, segment) in segments.iter().enumerate() {
+                                    if k > 0 {
+                                        arena.push(last);
+                                    }
+                                    last = Node {
+                                        parent: (arena.len() - 1) as u32,
+                                        box_pos: (segment.box_pos.0 * macro_h.m + segment.box_pos.1)
+                                            as u32,
+                                        direction: segment.direction,
+                                        pushes: segment.pushes,
+                                    };
+                                }
+                                let new_cost = cost + walk_len + new_node.pushes as usize + moves;
+                                let new_est_rest = self.calc_est_rest(&macro_h).unwrap();
+                                if new_cost + new_est_rest >= bound {
+                                    break;
+                                }
+                                que.push(Reverse(State {
+                                    g: macro_h,
+                                    node: last,
+                                    cost: new_cost,
+                                    est_rest: new_est_rest,
+                                    priority: strategy.priority(new_cost, new_est_rest),
+                                    seq: *next_seq,
+                                }));
+                                *next_seq += 1;
+                            }
+                            break;
+                        }
+                    }
+                    self.encode(&new_h, keying, &mut key);
+                    if visited.contains(&key) {
+                        continue;
+                    }
+                    let new_cost = cost + walk_len + new_node.pushes as usize;
+                    let new_est_rest = self.calc_est_rest(&new_h).unwrap();
+                    if new_cost + new_est_rest >= bound {
+                        continue;
+                    }
+                    que.push(Reverse(State {
+                        g: new_h.clone(),
+                        node: new_node,
+                        cost: new_cost,
+                        est_rest: new_est_rest,
+                        priority: strategy.priority(new_cost, new_est_rest),
+                        seq: *next_seq,
+                    }));
+                    *next_seq += 1;
+                }
+            }
+        }
+        if let Some(checkpoint) = checkpoint {
+            let _ = fs::remove_file(&checkpoint.path);
+        }
+        if let Some(certificate) = certificate {
+            certificate.states = snapshot.visited.len();
+            certificate.pruned = self
+                .detectors
+                .iter()
+                .map(|detector| detector.kind())
+                .zip(snapshot.pruned.iter().copied())
+                .collect();
+            certificate.explored_hash = proof::explored_hash(&snapshot.visited);
+        }
+        Err(SolverError::Unsolvable)
+    }
+}
+
+/// Loads one of the levels under `levels`, e.g. `"legacy/5"`.
+#[cfg(test)]
+fn test_level(name: &str) -> Board {
+    let raw = fs::read_to_string(format!("levels/{}.txt", name)).unwrap();
+    Board::from(raw.as_str())
+}
+
+/// Plays `seq` on `g`, which has to win the level.
+#[cfg(test)]
+fn assert_solves(g: &Board, seq: &[BoardCommand]) {
+    let mut replay = g.clone();
+    for &command in seq {
+        replay.execute(command);
+    }
+    assert!(replay.is_finished());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Board;
+    use super::BoardCommand;
+    use super::DeltaBoard;
+    use super::Solver;
+    use super::{assert_solves, test_level};
+    use super::{
+        CancellationToken, Limit, SearchStrategy, SolverConfig, SolverError, SolverLimits,
+    };
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    #[test]
+    fn test_execute_0() {
+        let g = Board::from(
+            "#######\n\
+             #.$@$.#\n\
+             #######",
+        );
+        let mut h = DeltaBoard::from(&g);
+        h.execute(BoardCommand::Left);
+        assert_eq!(
+            h,
+            (&Board::from(
+                "#######\n\
+                 #*@ $.#\n\
+                 #######",
+            ))
+                .into()
+        )
+    }
+
+    #[test]
+    fn test_execute_1() {
+        let g = Board::from(
+            "#########\n\
+             #..$$@$.#\n\
+             #########",
+        );
+        let mut h = DeltaBoard::from(&g);
+        h.execute(BoardCommand::Left);
+        assert_eq!(
+            h,
+            (&Board::from(
+                "#########\n\
+                 #..$$@$.#\n\
+                 #########",
+            ))
+                .into()
+        )
+    }
+
+    #[test]
+    fn test_execute_2() {
+        // swapping which box went where must land on the same zobrist hash & occupancy
+        let g = Board::from(
+            "#######\n\
+             #  $  #\n\
+             # $@  #\n\
+             #     #\n\
+             #######",
+        );
+        let mut h = DeltaBoard::from(&g);
+        h.execute(BoardCommand::Down);
+        h.execute(BoardCommand::Left);
+        h.execute(BoardCommand::Up);
+        let expected_g = Board::from(
+            "#######\n\
+             # $$  #\n\
+             # @   #\n\
+             #     #\n\
+             #######",
+        );
+        let expected = DeltaBoard::from(&expected_g);
+        assert_eq!(h, expected);
+        assert_eq!(h.zobrist, expected.zobrist);
+    }
+
+    #[test]
+    fn test_normalize_0() {
+        // same boxes, player in the same region but on different squares
+        let g_0 = Board::from(
+            "#######\n\
+             #@ #  #\n\
+             # $# .#\n\
+             #  $  #\n\
+             #######",
+        );
+        let g_1 = Board::from(
+            "#######\n\
+             #  #  #\n\
+             # $# .#\n\
+             #@ $  #\n\
+             #######",
+        );
+        let g_2 = Board::from(
+            "#######\n\
+             #  #@ #\n\
+             # $# .#\n\
+             # .$  #\n\
+             #######",
+        );
+        assert_eq!(DeltaBoard::from(&g_0), DeltaBoard::from(&g_1));
+        // walled off from the left region
+        assert_ne!(DeltaBoard::from(&g_2), DeltaBoard::from(&g_0));
+        // a push grows the region towards the top-left
+        let g_3 = Board::from(
+            "#######\n\
+             #. $@ #\n\
+             #######",
+        );
+        let mut h = DeltaBoard::from(&g_3);
+        assert_eq!(h.norm, 7 + 4);
+        h.execute(BoardCommand::Right);
+        assert_eq!(h.norm, 7 + 4);
+        h.execute(BoardCommand::Left);
+        h.execute(BoardCommand::Left);
+        assert_eq!(h.norm, 7 + 3);
+    }
+
+    #[test]
+    fn test_next_pushes_0() {
+        let g = Board::from(
+            "#######\n\
+             #.$@$.#\n\
+             #######",
+        );
+        let set = Solver::get_next_pushes(&(&g).into())
+            .into_iter()
+            .map(|(_b, _steps, dir)| dir)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            set,
+            HashSet::from([BoardCommand::Left, BoardCommand::Right])
+        );
+    }
+    #[test]
+    fn test_next_pushes_1() {
+        let g = Board::from(
+            "#######\n\
+             #  .  #\n\
+             #  $  #\n\
+             #.$@$.#\n\
+             #  $  #\n\
+             #  .  #\n\
+             #######",
+        );
+        let set = Solver::get_next_pushes(&(&g).into())
+            .into_iter()
+            .map(|(_b, _steps, dir)| dir)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            set,
+            HashSet::from([
+                BoardCommand::Left,
+                BoardCommand::Right,
+                BoardCommand::Up,
+                BoardCommand::Down
+            ])
+        );
+    }
+
+    #[test]
+    fn test_next_pushes_2() {
+        let g = Board::from(
+            "#######\n\
+             #  .$ #\n\
+             #  @ .#\n\
+             #    $#\n\
+             #     #\n\
+             #######",
+        );
+        let set = Solver::get_next_pushes(&(&g).into())
+            .into_iter()
+            .map(|(_b, _steps, dir)| dir)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            set,
+            HashSet::from([
+                BoardCommand::Left,
+                BoardCommand::Right,
+                BoardCommand::Up,
+                BoardCommand::Down
+            ])
+        );
+    }
+
+    #[test]
+    fn test_solve_0() {
+        let g = Board::from(
+            "#######\n\
+             #  .$ #\n\
+             #  @ .#\n\
+             #    $#\n\
+             #     #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        assert_eq!(
+            solver.solve(&CancellationToken::new()).unwrap().seq,
+            vec![
+                BoardCommand::Right,
+                BoardCommand::Right,
+                BoardCommand::Up,
+                BoardCommand::Left,
+                BoardCommand::Down,
+                BoardCommand::Down,
+                BoardCommand::Down,
+                BoardCommand::Right,
+                BoardCommand::Up,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_solve_1() {
+        let g = Board::from(
+            "#######\n\
+             #  .  #\n\
+             #  @  #\n\
+             #  $  #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        assert_eq!(
+            solver.solve(&CancellationToken::new()),
+            Err(SolverError::Unsolvable)
+        );
+    }
+
+    #[test]
+    fn test_solve_2() {
+        let g = Board::from(
+            "$###\n\
+              #@#\n\
+              ###",
+        );
+        let solver = Solver::new(&g);
+        assert_eq!(
+            solver.solve(&CancellationToken::new()),
+            Err(SolverError::UnreachableBox { pos: (0, 0) })
+        );
+    }
+
+    #[test]
+    fn test_solve_3() {
+        let g = Board::from(
+            "#######\n\
+             #     #\n\
+             # +$# #\n\
+             # **  #\n\
+             # *   #\n\
+             #######",
+        );
+        let solver = Solver::new(&g);
+        assert!(solver.solve(&CancellationToken::new()).is_ok());
+    }
+    #[test]
+    fn test_solve_4() {
+        // walks between pushes are rebuilt from the parent pointers, replaying them must win
+        let g = test_level("legacy/5");
+        let solver = Solver::new(&g);
+        let solution = solver.solve(&CancellationToken::new()).unwrap();
+        assert_solves(&g, &solution.seq);
+    }
+
+    #[test]
+    fn test_solve_6() {
+        // a PI-corral is there to be opened at some point, but pushing into it right away costs 2 more moves
+        let g = test_level("cognitive/4");
+        let solution = Solver::new(&g).solve(&CancellationToken::new()).unwrap();
+        assert_eq!(solution.seq.len(), 44);
+        assert!(solution.optimal);
+        // with the pruning on, nothing found is claimed to be optimal
+        let g = test_level("legacy/5");
+        let solver = Solver::with_config(
+            &g,
+            SolverConfig {
+                pi_corrals: true,
+                ..Default::default()
+            },
+        );
+        assert!(!solver.solve(&CancellationToken::new()).unwrap().optimal);
+    }
+
+    #[test]
+    fn test_in_tunnel_0() {
+        let g = Board::from(
+            "########\n\
+             #@$   .#\n\
+             ### ####\n\
+             ########",
+        );
+        let solver = Solver::new(&g);
+        let mut h = DeltaBoard::from(&g);
+        // first the box, then the player still has a way out below
+        h.execute(BoardCommand::Right);
+        assert!(!solver.in_tunnel(&h, (1, 3), (0, 1)));
+        h.execute(BoardCommand::Right);
+        assert!(!solver.in_tunnel(&h, (1, 4), (0, 1)));
+        h.execute(BoardCommand::Right);
+        assert!(solver.in_tunnel(&h, (1, 5), (0, 1)));
+        // never carry a box past a goal
+        h.execute(BoardCommand::Right);
+        assert!(!solver.in_tunnel(&h, (1, 6), (0, 1)));
+    }
+
+    #[test]
+    fn test_solve_5() {
+        // boxes entering the goal room are brought to their goals by a macro move
+        let g = Board::from(
+            "##########\n\
+             #...  #  #\n\
+             ####  $  #\n\
+             ___#  $$@#\n\
+             ___#     #\n\
+             ___#######",
+        );
+        let solver = Solver::new(&g);
+        assert_eq!(solver.goal_rooms.len(), 1);
+        let solution = solver.solve(&CancellationToken::new()).unwrap();
+        assert_solves(&g, &solution.seq);
+    }
+
+    #[test]
+    fn test_limits_0() {
+        let g = test_level("legacy/5");
+        let solver = Solver::with_config(
+            &g,
+            SolverConfig {
+                limits: SolverLimits {
+                    max_states: Some(3),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        match solver.solve(&CancellationToken::new()) {
+            Err(SolverError::LimitExceeded {
+                limit: Limit::States,
+                stats,
+            }) => {
+                assert_eq!(stats.expanded, 3);
+                assert!(stats.frontier > 0);
+            }
+            res => panic!("expected to run out of states, got {:?}", res),
+        }
+        let solver = Solver::with_config(
+            &g,
+            SolverConfig {
+                limits: SolverLimits {
+                    max_memory: Some(0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            solver.solve(&CancellationToken::new()),
+            Err(SolverError::LimitExceeded {
+                limit: Limit::Memory,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_strategy_0() {
+        // the faster strategies still have to win, but may take more than the 20 moves A* finds
+        let g = test_level("legacy/6");
+        let optimal = Solver::new(&g).solve(&CancellationToken::new()).unwrap();
+        assert!(optimal.optimal);
+        assert_eq!(optimal.seq.len(), 20);
+        for strategy in [SearchStrategy::Weighted(300), SearchStrategy::Greedy] {
+            let solver = Solver::with_config(
+                &g,
+                SolverConfig {
+                    strategy,
+                    ..Default::default()
+                },
+            );
+            let solution = solver.solve(&CancellationToken::new()).unwrap();
+            assert!(!solution.optimal);
+            assert!(solution.seq.len() >= 20);
+            assert_solves(&g, &solution.seq);
+        }
+    }
+
+    #[test]
+    fn test_anytime_0() {
+        // greedy search takes 57 moves, weighted A* 46 and A* the optimal 44
+        let g = test_level("legacy/1");
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let solution = Solver::new(&g)
+            .solve_anytime(&CancellationToken::new(), &sender)
+            .unwrap();
+        assert!(solution.optimal);
+        assert_eq!(solution.seq.len(), 44);
+        // every improvement is shorter than the one before, the last one being the proven optimum
+        let improvements = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(
+            improvements.iter().map(|v| v.seq.len()).collect::<Vec<_>>(),
+            vec![57, 46, 44]
+        );
+        assert_eq!(improvements.last(), Some(&solution));
+        // A* finding nothing shorter than the 22 moves weighted A* found proves them optimal
+        let g = test_level("legacy/5");
+        let solution = Solver::new(&g)
+            .solve_anytime(&CancellationToken::new(), &sender)
+            .unwrap();
+        assert!(solution.optimal);
+        let improvements = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(
+            improvements
+                .iter()
+                .map(|v| (v.seq.len(), v.optimal))
+                .collect::<Vec<_>>(),
+            vec![(24, false), (22, false), (22, true)]
+        );
+        // PI-corrals may have cut off something shorter
+        let solver = Solver::with_config(
+            &g,
+            SolverConfig {
+                pi_corrals: true,
+                ..Default::default()
+            },
+        );
+        let solution = solver
+            .solve_anytime(&CancellationToken::new(), &sender)
+            .unwrap();
+        assert!(!solution.optimal);
+    }
+
+    #[test]
+    fn test_cancelled_0() {
+        let g = test_level("legacy/5");
+        let solver = Solver::new(&g);
+        let token = CancellationToken::new();
+        let child = token.child();
+        token.cancel();
+        assert_eq!(solver.solve(&child), Err(SolverError::Cancelled));
+        assert!(solver.solve(&CancellationToken::new()).is_ok());
+    }
+
+    #[test]
+    fn test_progress_0() {
+        let g = test_level("legacy/5");
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        // no throttling, one report per expanded state
+        let solution = Solver::new(&g)
+            .with_progress(sender, Duration::ZERO)
+            .solve(&CancellationToken::new())
+            .unwrap();
+        let reports = receiver.iter().collect::<Vec<_>>();
+        assert_eq!(reports.len(), solution.visited_states);
+        for (k, progress) in reports.iter().enumerate() {
+            assert_eq!(progress.stats.expanded, k + 1);
+            assert_eq!(progress.board.num_box, g.num_box);
+        }
+        assert_eq!(reports[0].board.cells, g.cells);
+    }
+
+    #[test]
+    fn test_insolvable_0() {
+        let g = Board::from(
+            "#######\n\
+             #  .$ #\n\
+             #  @ .#\n\
+             #    $#\n\
+             #     #\n\
+             #######",
+        );
+        let insolvable = vec![
+            vec![true; 7],
+            vec![true, true, false, false, false, true, true],
+            vec![true, true, false, false, false, false, true],
+            vec![true, true, false, false, false, false, true],
+            vec![true; 7],
+            vec![true; 7],
+        ];
+        let solver = Solver::new(&g);
+        assert_eq!(solver.insolvable, insolvable)
+    }
+}
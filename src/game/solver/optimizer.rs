@@ -0,0 +1,454 @@
+use super::{direction_delta, DeltaBoard, DIRECTIONS};
+use crate::game::board::Board;
+use crate::game::board_command::BoardCommand;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+/// How many consecutive pushes a window may hold when searching for a shorter way through it.
+const WINDOW: usize = 6;
+/// How many states one window search may look at.
+const WINDOW_SEARCH_LIMIT: usize = 5000;
+/// How often window search and push reordering take turns before giving up on finding more.
+const MAX_ROUNDS: usize = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptimizeError {
+    InvalidLurd { pos: usize, ch: char },
+    IllegalMove { step: usize }, // the player would walk into a wall or a stuck box
+    NotSolved,
+}
+
+impl std::fmt::Display for OptimizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLurd { pos, ch } => {
+                write!(f, "Unexpected {:?} at {} in LURD string", ch, pos + 1)
+            }
+            Self::IllegalMove { step } => write!(f, "Move {} can't be made", step + 1),
+            Self::NotSolved => write!(f, "The moves don't solve the level"),
+        }
+    }
+}
+
+impl std::error::Error for OptimizeError {}
+
+/// A shorter solution together with how much it saves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Optimized {
+    pub seq: Vec<BoardCommand>,
+    pub moves_before: usize,
+    pub pushes_before: usize,
+    pub moves_after: usize,
+    pub pushes_after: usize,
+}
+
+impl std::fmt::Display for Optimized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Saved {} moves and {} pushes: {}/{} down to {}/{}",
+            self.moves_before - self.moves_after,
+            self.pushes_before as isize - self.pushes_after as isize,
+            self.moves_before,
+            self.pushes_before,
+            self.moves_after,
+            self.pushes_after
+        )
+    }
+}
+
+/// Reads moves written as `l`, `u`, `r` and `d`, upper case marking pushes. Whitespace is skipped.
+pub fn parse_lurd(raw: &str) -> Result<Vec<BoardCommand>, OptimizeError> {
+    raw.chars()
+        .enumerate()
+        .filter(|(_, ch)| !ch.is_whitespace())
+        .map(|(pos, ch)| match ch.to_ascii_lowercase() {
+            'l' => Ok(BoardCommand::Left),
+            'u' => Ok(BoardCommand::Up),
+            'r' => Ok(BoardCommand::Right),
+            'd' => Ok(BoardCommand::Down),
+            _ => Err(OptimizeError::InvalidLurd { pos, ch }),
+        })
+        .collect()
+}
+
+/// Writes `seq` as played on `g` in LURD notation, pushes in upper case.
+pub fn format_lurd(g: &Board, seq: &[BoardCommand]) -> String {
+    let mut h = DeltaBoard::from(g);
+    seq.iter()
+        .map(|&command| {
+            let ch = match command {
+                BoardCommand::Left => 'l',
+                BoardCommand::Up => 'u',
+                BoardCommand::Right => 'r',
+                BoardCommand::Down => 'd',
+                BoardCommand::Null => return ' ',
+            };
+            match h.execute(command).0 {
+                Some(_) => ch.to_ascii_uppercase(),
+                None => ch,
+            }
+        })
+        .filter(|ch| *ch != ' ')
+        .collect()
+}
+
+/// Pushing the box at `box_pos` one square along `direction`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Push {
+    box_pos: (usize, usize),
+    direction: BoardCommand,
+}
+
+impl Push {
+    fn behind(&self) -> (usize, usize) {
+        let (di, dj) = direction_delta(self.direction);
+        Board::get_next(self.box_pos, (di.wrapping_neg(), dj.wrapping_neg()))
+    }
+
+    fn ahead(&self) -> (usize, usize) {
+        Board::get_next(self.box_pos, direction_delta(self.direction))
+    }
+}
+
+/// The pushes `seq` makes on `g`, checking that every move can be made and that the level ends up solved.
+fn extract_pushes(g: &Board, seq: &[BoardCommand]) -> Result<Vec<Push>, OptimizeError> {
+    let mut h = DeltaBoard::from(g);
+    let mut pushes = vec![];
+    for (step, &direction) in seq.iter().enumerate() {
+        let (box_moved, player_moved) = h.execute(direction);
+        if !player_moved {
+            return Err(OptimizeError::IllegalMove { step });
+        }
+        if box_moved.is_some() {
+            // the player now stands where the box was
+            pushes.push(Push {
+                box_pos: (h.i, h.j),
+                direction,
+            });
+        }
+    }
+    match h.is_finished() {
+        true => Ok(pushes),
+        false => Err(OptimizeError::NotSolved),
+    }
+}
+
+/// Plays `pushes` on `g` with the shortest walks in between. Gives the board afterwards, the moves, and the index of
+/// every push among the moves, or `None` if some push can't be made.
+fn replay<'a>(
+    g: &'a Board,
+    pushes: &[Push],
+) -> Option<(DeltaBoard<'a>, Vec<BoardCommand>, Vec<usize>)> {
+    let mut h = DeltaBoard::from(g);
+    let mut moves = vec![];
+    let mut at = vec![];
+    for push in pushes {
+        let (ai, aj) = push.ahead();
+        if !h.has_box_at(push.box_pos.0, push.box_pos.1) || !h.is_free(ai, aj) {
+            return None;
+        }
+        let reachable = h.reachable();
+        let (bi, bj) = push.behind();
+        if !h.pos_is_valid(bi, bj) || reachable[bi * h.m + bj].is_none() {
+            return None;
+        }
+        moves.append(&mut h.walk_to(&reachable, (bi, bj)));
+        (h.i, h.j) = (bi, bj);
+        h.execute(push.direction);
+        at.push(moves.len());
+        moves.push(push.direction);
+    }
+    Some((h, moves, at))
+}
+
+/// Moves and pushes of a full solution made of `pushes`, `None` unless it is legal and solves the level.
+fn cost_of(g: &Board, pushes: &[Push]) -> Option<(usize, usize)> {
+    replay(g, pushes)
+        .filter(|(h, _, _)| h.is_finished())
+        .map(|(_, moves, _)| (moves.len(), pushes.len()))
+}
+
+/// Looks for pushes taking `start` to the box positions of `target` in fewer (moves, pushes) than `budget`, counting
+/// the walk up to `next` too. Boxes standing on `fixed` squares are left alone.
+fn search_window(
+    start: &DeltaBoard<'_>,
+    target: &DeltaBoard<'_>,
+    next: Option<Push>,
+    fixed: &[(usize, usize)],
+    budget: (usize, usize),
+) -> Option<Vec<Push>> {
+    let mut nodes = vec![(start.clone(), usize::MAX, None)];
+    let mut que = BinaryHeap::from([Reverse((0, 0, 0))]);
+    let mut visited = HashSet::new();
+    let mut best = budget;
+    let mut found = None;
+    while let Some(Reverse((moves, pushes, idx))) = que.pop() {
+        if (moves, pushes) >= best {
+            break;
+        }
+        let h = nodes[idx].0.clone();
+        if !visited.insert((h.occupancy.clone(), h.i * h.m + h.j)) {
+            continue;
+        }
+        if visited.len() > WINDOW_SEARCH_LIMIT {
+            break;
+        }
+        let reachable = h.reachable();
+        if h.occupancy == target.occupancy {
+            let tail = match next {
+                Some(push) => {
+                    let (bi, bj) = push.behind();
+                    match reachable[bi * h.m + bj] {
+                        Some((_, d)) => d,
+                        None => continue,
+                    }
+                }
+                None => 0,
+            };
+            if (moves + tail, pushes) < best {
+                best = (moves + tail, pushes);
+                found = Some(idx);
+            }
+            continue;
+        }
+        for &(i, j) in h.boxes.iter().filter(|pos| !fixed.contains(pos)) {
+            for (direction, _) in DIRECTIONS {
+                let push = Push {
+                    box_pos: (i, j),
+                    direction,
+                };
+                let ((bi, bj), (ai, aj)) = (push.behind(), push.ahead());
+                if !h.pos_is_valid(bi, bj) || !h.is_free(ai, aj) {
+                    continue;
+                }
+                let Some((_, walk_len)) = reachable[bi * h.m + bj] else {
+                    continue;
+                };
+                let next_cost = (moves + walk_len + 1, pushes + 1);
+                if next_cost >= best {
+                    continue;
+                }
+                let mut new_h = h.clone();
+                (new_h.i, new_h.j) = (bi, bj);
+                new_h.execute(direction);
+                nodes.push((new_h, idx, Some(push)));
+                que.push(Reverse((next_cost.0, next_cost.1, nodes.len() - 1)));
+            }
+        }
+    }
+    let mut res = vec![];
+    let mut cur = found?;
+    while let (_, parent, Some(push)) = &nodes[cur] {
+        res.push(*push);
+        cur = *parent;
+    }
+    res.reverse();
+    Some(res)
+}
+
+/// Runs `search_window` over every window of pushes, keeping whatever makes the whole solution cheaper.
+fn improve_windows(g: &Board, pushes: &mut Vec<Push>, cost: &mut (usize, usize)) -> bool {
+    let mut improved = false;
+    let mut a = 0;
+    while a < pushes.len() {
+        let b = (a + WINDOW).min(pushes.len());
+        let (start, _, _) = replay(g, &pushes[..a]).unwrap();
+        let (target, moves, at) = replay(g, &pushes[..b]).unwrap();
+        // from the walk to push `a` up to, but not including, push `b`
+        let begin = match a {
+            0 => 0,
+            _ => at[a - 1] + 1,
+        };
+        let end = match pushes.get(b) {
+            Some(push) => {
+                let reachable = target.reachable();
+                let (bi, bj) = push.behind();
+                moves.len() + reachable[bi * target.m + bj].unwrap().1
+            }
+            None => moves.len(),
+        };
+        // boxes the window doesn't touch stay where they are
+        let moved = pushes[a..b]
+            .iter()
+            .map(|push| push.ahead())
+            .collect::<Vec<_>>();
+        let fixed = target
+            .boxes
+            .iter()
+            .copied()
+            .filter(|pos| !moved.contains(pos))
+            .collect::<Vec<_>>();
+        if let Some(window) = search_window(
+            &start,
+            &target,
+            pushes.get(b).copied(),
+            &fixed,
+            (end - begin, b - a),
+        ) {
+            let mut candidate = pushes[..a].to_vec();
+            candidate.extend(window);
+            candidate.extend_from_slice(&pushes[b..]);
+            if let Some(new_cost) = cost_of(g, &candidate).filter(|v| v < cost) {
+                *pushes = candidate;
+                *cost = new_cost;
+                improved = true;
+                continue;
+            }
+        }
+        a += 1;
+    }
+    improved
+}
+
+/// Swaps neighbouring runs of pushes on different boxes whenever that saves walking.
+fn improve_order(g: &Board, pushes: &mut Vec<Push>, cost: &mut (usize, usize)) -> bool {
+    let mut improved = false;
+    let mut changed = true;
+    while changed {
+        changed = false;
+        // a run ends where the next push is on a different box
+        let mut runs = vec![0];
+        for k in 1..pushes.len() {
+            if pushes[k].box_pos != pushes[k - 1].ahead() {
+                runs.push(k);
+            }
+        }
+        runs.push(pushes.len());
+        for r in 0..runs.len().saturating_sub(2) {
+            let (x, y, z) = (runs[r], runs[r + 1], runs[r + 2]);
+            let mut candidate = pushes[..x].to_vec();
+            candidate.extend_from_slice(&pushes[y..z]);
+            candidate.extend_from_slice(&pushes[x..y]);
+            candidate.extend_from_slice(&pushes[z..]);
+            if let Some(new_cost) = cost_of(g, &candidate).filter(|v| v < cost) {
+                *pushes = candidate;
+                *cost = new_cost;
+                improved = true;
+                changed = true;
+                break;
+            }
+        }
+    }
+    improved
+}
+
+/// Shortens a solution of `g`: walks between pushes become shortest walks, windows of a few pushes are searched again
+/// for a cheaper way to the same boxes, and runs of pushes on different boxes are swapped where that saves walking.
+/// The result is replayed on `g` before it is returned.
+pub fn optimize(g: &Board, seq: &[BoardCommand]) -> Result<Optimized, OptimizeError> {
+    let seq = seq
+        .iter()
+        .copied()
+        .filter(|command| *command != BoardCommand::Null)
+        .collect::<Vec<_>>();
+    let mut pushes = extract_pushes(g, &seq)?;
+    let pushes_before = pushes.len();
+    let mut cost = cost_of(g, &pushes).unwrap();
+    for _ in 0..MAX_ROUNDS {
+        let windows = improve_windows(g, &mut pushes, &mut cost);
+        let order = improve_order(g, &mut pushes, &mut cost);
+        if !windows && !order {
+            break;
+        }
+    }
+    let (_, mut moves, _) = replay(g, &pushes).unwrap();
+    if (moves.len(), pushes.len()) >= (seq.len(), pushes_before) {
+        moves = seq.clone();
+    }
+    // play it on a real board once more, so that whatever comes out is known to work
+    let mut check = g.clone();
+    for &command in moves.iter() {
+        check.execute(command);
+    }
+    if !check.is_finished() {
+        moves = seq.clone();
+    }
+    let pushes_after = extract_pushes(g, &moves)?.len();
+    Ok(Optimized {
+        moves_before: seq.len(),
+        pushes_before,
+        moves_after: moves.len(),
+        pushes_after,
+        seq: moves,
+    })
+}
+
+/// `optimize` for a solution written in LURD notation.
+pub fn optimize_lurd(g: &Board, raw: &str) -> Result<Optimized, OptimizeError> {
+    optimize(g, &parse_lurd(raw)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_lurd, optimize, optimize_lurd, parse_lurd, OptimizeError};
+    use crate::game::board::Board;
+    use crate::game::board_command::BoardCommand;
+    use crate::game::solver::{
+        assert_solves, test_level, CancellationToken, SearchStrategy, Solver, SolverConfig,
+    };
+
+    #[test]
+    fn test_lurd_0() {
+        let g = Board::from(
+            "#####\n\
+             #@$.#\n\
+             #####",
+        );
+        let seq = parse_lurd("R").unwrap();
+        assert_eq!(seq, vec![BoardCommand::Right]);
+        assert_eq!(format_lurd(&g, &seq), "R");
+        assert_eq!(
+            parse_lurd("lu rd\nx"),
+            Err(OptimizeError::InvalidLurd { pos: 6, ch: 'x' })
+        );
+    }
+
+    #[test]
+    fn test_optimize_0() {
+        let g = test_level("legacy/5");
+        let optimal = Solver::new(&g)
+            .solve(&CancellationToken::new())
+            .unwrap()
+            .seq;
+        assert_eq!(optimal.len(), 22);
+        // wander around before solving it
+        let mut seq = parse_lurd("uddu").unwrap();
+        seq.extend(optimal.iter().copied());
+        let res = optimize(&g, &seq).unwrap();
+        assert_eq!((res.moves_before, res.moves_after), (26, 22));
+        assert_solves(&g, &res.seq);
+        // greedy search takes 2 moves more
+        let solver = Solver::with_config(
+            &g,
+            SolverConfig {
+                strategy: SearchStrategy::Greedy,
+                ..Default::default()
+            },
+        );
+        let greedy = solver.solve(&CancellationToken::new()).unwrap().seq;
+        let res = optimize(&g, &greedy).unwrap();
+        assert_eq!((res.moves_before, res.moves_after), (24, 22));
+        assert_solves(&g, &res.seq);
+    }
+
+    #[test]
+    fn test_optimize_1() {
+        let g = Board::from(
+            "#########\n\
+             #       #\n\
+             # $ @ $ #\n\
+             # .   . #\n\
+             #########",
+        );
+        let res = optimize_lurd(&g, "ullDurrrrlrD").unwrap();
+        assert_eq!((res.moves_before, res.pushes_before), (12, 2));
+        assert_eq!((res.moves_after, res.pushes_after), (10, 2));
+        assert_eq!(format_lurd(&g, &res.seq).len(), 10);
+        assert_eq!(optimize_lurd(&g, "ullD"), Err(OptimizeError::NotSolved));
+        assert_eq!(
+            optimize_lurd(&g, "dd"),
+            Err(OptimizeError::IllegalMove { step: 1 })
+        );
+    }
+}
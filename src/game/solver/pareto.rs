@@ -0,0 +1,252 @@
+use super::state_table::StateTable;
+use super::{
+    direction_delta, CancellationToken, DeltaBoard, Keying, Node, SearchStats, Solution, Solver,
+    SolverError,
+};
+use crate::game::board::Board;
+use crate::game::board_command::BoardCommand;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::Instant;
+
+/// A solution nothing else beats in moves without needing more pushes, or in pushes without needing more moves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrontPoint {
+    pub moves: usize,
+    pub pushes: usize,
+    pub solution: Solution,
+}
+
+/// A way into a board, with what it cost in moves and pushes so far.
+struct Label<'a> {
+    g: DeltaBoard<'a>,
+    node: Node,
+    moves: usize,
+    pushes: usize,
+    est_rest: usize, // pushes still needed at least, and so moves too
+    seq: u64,
+}
+
+impl Label<'_> {
+    fn key(&self) -> (usize, usize, u64) {
+        (
+            self.moves + self.est_rest,
+            self.pushes + self.est_rest,
+            self.seq,
+        )
+    }
+}
+
+impl PartialEq for Label<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Label<'_> {}
+
+impl PartialOrd for Label<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Label<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // fewest moves first, then fewest pushes
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Solver<'_> {
+    /// Finds the Pareto front of moves against pushes, from the solution with the fewest moves to the one with the
+    /// fewest pushes. Each point on the front comes with one solution, among those with the same moves and pushes.
+    pub fn solve_pareto(&self, cancel: &CancellationToken) -> Result<Vec<FrontPoint>, SolverError> {
+        let res = self.search_pareto(cancel);
+        for detector in self.detectors.iter() {
+            detector.finish();
+        }
+        res
+    }
+
+    /// Bi-objective A* over single pushes keyed by the exact player square. Labels come out of the open list by
+    /// estimated moves, then pushes, so a label is dominated once an earlier one at the same state, or a solution,
+    /// needed no more pushes. Like `solve_all_optimal`, it leaves out PI-corrals, tunnels and goal rooms.
+    fn search_pareto(&self, cancel: &CancellationToken) -> Result<Vec<FrontPoint>, SolverError> {
+        let start = Instant::now();
+        let limits = self.config.limits;
+        let init_delta_board = DeltaBoard::from(self.board);
+        let est_rest = self
+            .calc_est_rest(&init_delta_board)
+            .map_err(|pos| SolverError::UnreachableBox { pos })?;
+        let mut que = BinaryHeap::from([Reverse(Label {
+            g: init_delta_board,
+            node: Node {
+                parent: Node::ROOT,
+                box_pos: 0,
+                direction: BoardCommand::Null,
+                pushes: 0,
+            },
+            moves: 0,
+            pushes: 0,
+            est_rest,
+            seq: 0,
+        })]);
+        let mut visited = StateTable::new(self.board.num_box + 1);
+        let mut min_pushes: Vec<usize> = vec![]; // per state, the fewest pushes it has been expanded with
+        let mut arena = vec![];
+        let mut pruned = vec![0; self.detectors.len()];
+        let mut front: Vec<FrontPoint> = vec![];
+        let mut next_seq = 1;
+        let mut best_h = usize::MAX;
+        let mut key = Vec::with_capacity(self.board.num_box + 1);
+        while let Some(Reverse(Label {
+            g: h,
+            node,
+            moves,
+            pushes,
+            est_rest,
+            ..
+        })) = que.pop()
+        {
+            if cancel.is_cancelled() {
+                return Err(SolverError::Cancelled);
+            }
+            let memory = visited.memory_usage()
+                + arena.capacity() * std::mem::size_of::<Node>()
+                + que.len() * self.state_bytes();
+            if let Some(limit) = limits.exceeded(arena.len(), start.elapsed(), memory) {
+                let stats = SearchStats {
+                    expanded: arena.len(),
+                    frontier: que.len(),
+                    best_h,
+                    elapsed: start.elapsed(),
+                };
+                return Err(SolverError::LimitExceeded { limit, stats });
+            }
+            // every solution found so far needed no more moves
+            if front
+                .last()
+                .is_some_and(|point| pushes + est_rest >= point.pushes)
+            {
+                continue;
+            }
+            self.encode(&h, Keying::Exact, &mut key);
+            let (idx, new) = visited.insert(&key);
+            if new {
+                min_pushes.push(usize::MAX);
+            }
+            if pushes >= min_pushes[idx] {
+                continue;
+            }
+            min_pushes[idx] = pushes;
+            best_h = best_h.min(est_rest);
+            arena.push(node);
+            if h.is_finished() {
+                front.push(FrontPoint {
+                    moves,
+                    pushes,
+                    solution: Solution {
+                        seq: self.rebuild_steps(&arena, arena.len() - 1),
+                        visited_states: arena.len(),
+                        pruned: self
+                            .detectors
+                            .iter()
+                            .map(|detector| detector.kind())
+                            .zip(pruned.iter().copied())
+                            .collect(),
+                        optimal: front.is_empty(),
+                    },
+                });
+                continue;
+            }
+            let parent = (arena.len() - 1) as u32;
+            for (mut new_h, walk_len, direction) in Self::get_next_pushes_from(&h, &h.reachable()) {
+                let (bi, bj) = Board::get_next((new_h.i, new_h.j), direction_delta(direction));
+                let (Some(box_pos), true) = new_h.execute(direction) else {
+                    continue;
+                };
+                if let Some(k) = self
+                    .detectors
+                    .iter()
+                    .position(|detector| detector.is_deadlock(self, &new_h, box_pos))
+                {
+                    pruned[k] += 1;
+                    continue;
+                }
+                let Ok(new_est_rest) = self.calc_est_rest(&new_h) else {
+                    continue;
+                };
+                que.push(Reverse(Label {
+                    g: new_h,
+                    node: Node {
+                        parent,
+                        box_pos: (bi * self.board.m + bj) as u32,
+                        direction,
+                        pushes: 1,
+                    },
+                    moves: moves + walk_len + 1,
+                    pushes: pushes + 1,
+                    est_rest: new_est_rest,
+                    seq: next_seq,
+                }));
+                next_seq += 1;
+            }
+        }
+        match front.is_empty() {
+            true => Err(SolverError::Unsolvable),
+            false => Ok(front),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::Board;
+    use crate::game::solver::{
+        assert_solves, test_level, CancellationToken, DeltaBoard, Solver, SolverError,
+    };
+
+    #[test]
+    fn test_pareto_0() {
+        let g = test_level("legacy/5");
+        let front = Solver::new(&g)
+            .solve_pareto(&CancellationToken::new())
+            .unwrap();
+        let points = front
+            .iter()
+            .map(|point| (point.moves, point.pushes))
+            .collect::<Vec<_>>();
+        assert_eq!(points, vec![(22, 7), (24, 5)]);
+        assert!(front[0].solution.optimal && !front[1].solution.optimal);
+        for point in front.iter() {
+            assert_solves(&g, &point.solution.seq);
+            let mut h = DeltaBoard::from(&g);
+            let pushes = point
+                .solution
+                .seq
+                .iter()
+                .filter(|&&command| h.execute(command).0.is_some())
+                .count();
+            assert_eq!(
+                (point.solution.seq.len(), pushes),
+                (point.moves, point.pushes)
+            );
+        }
+    }
+
+    #[test]
+    fn test_pareto_1() {
+        let g = Board::from(
+            "#######\n\
+             #. @ .#\n\
+             # $#$ #\n\
+             #  #  #\n\
+             #######",
+        );
+        assert_eq!(
+            Solver::new(&g).solve_pareto(&CancellationToken::new()),
+            Err(SolverError::Unsolvable)
+        );
+    }
+}
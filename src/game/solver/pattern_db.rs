@@ -0,0 +1,279 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// How many states the sub-search of `PatternDatabase::learn` may look at before giving up on a pattern.
+const PATTERN_SEARCH_LIMIT: usize = 2000;
+
+/// One square of a window, as stored in a pattern key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatternCell {
+    Floor,
+    Goal,
+    Wall,
+    Box,
+    BoxOnGoal,
+    Player,
+    PlayerOnGoal,
+}
+
+impl PatternCell {
+    fn code(self) -> u64 {
+        self as u64
+    }
+
+    fn from_code(code: u64) -> Self {
+        match code {
+            0 => Self::Floor,
+            1 => Self::Goal,
+            2 => Self::Wall,
+            3 => Self::Box,
+            4 => Self::BoxOnGoal,
+            5 => Self::Player,
+            _ => Self::PlayerOnGoal,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    Deadlock,
+    Safe,
+    Unknown, // the sub-search ran out of budget
+}
+
+/// Packs a window of at most 4x4 squares, given row by row, into a key.
+pub fn pattern_key(h: usize, w: usize, cells: &[PatternCell]) -> u64 {
+    debug_assert!((1..=4).contains(&h) && (1..=4).contains(&w) && cells.len() == h * w);
+    cells
+        .iter()
+        .enumerate()
+        .fold(((h - 1) << 2 | (w - 1)) as u64, |key, (k, cell)| {
+            key | cell.code() << (4 + 3 * k)
+        })
+}
+
+fn unpack_key(key: u64) -> (usize, usize, Vec<PatternCell>) {
+    let (h, w) = ((key >> 2 & 3) as usize + 1, (key & 3) as usize + 1);
+    let cells = (0..h * w)
+        .map(|k| PatternCell::from_code(key >> (4 + 3 * k) & 7))
+        .collect();
+    (h, w, cells)
+}
+
+/// Small box and wall configurations together with whether a sub-search could prove them unsolvable, so that they
+/// don't have to be proven again on every run.
+#[derive(Debug, Default)]
+pub struct PatternDatabase {
+    entries: HashMap<u64, Pattern>,
+}
+
+impl PatternDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a database written by `PatternDatabase::save`, a missing file gives an empty database.
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+        let mut res = Self::new();
+        match fs::read_to_string(path) {
+            Ok(raw) => res.merge(&raw),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        Ok(res)
+    }
+
+    fn merge(&mut self, raw: &str) {
+        for line in raw.lines().filter(|line| !line.starts_with('#')) {
+            let mut it = line.split_whitespace();
+            let (Some(key), Some(kind)) = (it.next(), it.next()) else {
+                continue;
+            };
+            let Ok(key) = u64::from_str_radix(key, 16) else {
+                continue;
+            };
+            let pattern = match kind {
+                "D" => Pattern::Deadlock,
+                "S" => Pattern::Safe,
+                "U" => Pattern::Unknown,
+                _ => continue,
+            };
+            self.entries.entry(key).or_insert(pattern);
+        }
+    }
+
+    /// Writes every entry to `path`, keeping whatever other runs have stored there in the meantime.
+    pub fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+        let mut all = Self::load(path)?;
+        all.entries
+            .extend(self.entries.iter().map(|(&key, &pattern)| (key, pattern)));
+        let mut keys: Vec<_> = all.entries.iter().collect();
+        keys.sort_by_key(|(key, _)| **key);
+        let mut out = std::io::BufWriter::new(fs::File::create(path)?);
+        writeln!(out, "# sokoban deadlock patterns")?;
+        for (key, pattern) in keys {
+            let kind = match pattern {
+                Pattern::Deadlock => "D",
+                Pattern::Safe => "S",
+                Pattern::Unknown => "U",
+            };
+            writeln!(out, "{:013x} {}", key, kind)?;
+        }
+        out.flush()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: u64) -> Option<Pattern> {
+        self.entries.get(&key).copied()
+    }
+
+    /// Looks `key` up, running the sub-search for it the first time it shows up.
+    pub fn lookup(&mut self, key: u64) -> Pattern {
+        *self.entries.entry(key).or_insert_with(|| Self::learn(key))
+    }
+
+    /// Decides a pattern on its own: everything around the window is free floor and boxes leaving the window are
+    /// gone for good. That is easier than any real board containing the pattern, so if not even then all boxes
+    /// inside can be put onto goals, the pattern is a deadlock wherever it shows up.
+    pub fn learn(key: u64) -> Pattern {
+        let (h, w, cells) = unpack_key(key);
+        // pad the window with a ring of floor the player can walk around on
+        let (ph, pw) = (h + 2, w + 2);
+        let mut wall = vec![false; ph * pw];
+        let mut goal = vec![false; ph * pw];
+        let mut boxes = 0u64;
+        let mut player = 0;
+        for (k, cell) in cells.iter().enumerate() {
+            let idx = (k / w + 1) * pw + k % w + 1;
+            match cell {
+                PatternCell::Wall => wall[idx] = true,
+                PatternCell::Goal | PatternCell::PlayerOnGoal => goal[idx] = true,
+                PatternCell::Box => boxes |= 1 << idx,
+                PatternCell::BoxOnGoal => {
+                    boxes |= 1 << idx;
+                    goal[idx] = true;
+                }
+                PatternCell::Floor | PatternCell::Player => {}
+            }
+            if matches!(cell, PatternCell::Player | PatternCell::PlayerOnGoal) {
+                player = idx;
+            }
+        }
+        let in_ring = |idx: usize| {
+            let (i, j) = (idx / pw, idx % pw);
+            i == 0 || i == ph - 1 || j == 0 || j == pw - 1
+        };
+        let step = |idx: usize, d: usize| match d {
+            0 => (idx >= pw).then(|| idx - pw),
+            1 => (idx + pw < ph * pw).then(|| idx + pw),
+            2 => (!idx.is_multiple_of(pw)).then(|| idx - 1),
+            _ => (idx % pw + 1 < pw).then(|| idx + 1),
+        };
+        let region = |boxes: u64, player: usize| {
+            // squares the player can walk to, as a bitset over the padded window
+            let mut seen = 1u64 << player;
+            let mut que = VecDeque::from([player]);
+            while let Some(idx) = que.pop_front() {
+                for d in 0..4 {
+                    if let Some(next) = step(idx, d) {
+                        if seen & 1 << next == 0 && !wall[next] && boxes & 1 << next == 0 {
+                            seen |= 1 << next;
+                            que.push_back(next);
+                        }
+                    }
+                }
+            }
+            seen
+        };
+        let goals = (0..ph * pw)
+            .filter(|idx| goal[*idx])
+            .fold(0u64, |acc, idx| acc | 1 << idx);
+        let mut visited = HashSet::new();
+        // boxes still off their goals first, most patterns turn out safe after emptying the window greedily
+        let mut que = BinaryHeap::new();
+        let seen = region(boxes, player);
+        visited.insert((boxes, seen.trailing_zeros()));
+        que.push(Reverse(((boxes & !goals).count_ones(), boxes, seen)));
+        while let Some(Reverse((off_goal, boxes, seen))) = que.pop() {
+            if off_goal == 0 {
+                return Pattern::Safe;
+            }
+            for idx in (0..ph * pw).filter(|idx| boxes & 1 << idx != 0) {
+                for d in 0..4 {
+                    let (Some(ahead), Some(behind)) = (step(idx, d), step(idx, d ^ 1)) else {
+                        continue;
+                    };
+                    if seen & 1 << behind == 0 || wall[ahead] || boxes & 1 << ahead != 0 {
+                        continue;
+                    }
+                    let mut next = boxes & !(1 << idx);
+                    if !in_ring(ahead) {
+                        next |= 1 << ahead;
+                    }
+                    let next_seen = region(next, idx);
+                    if visited.insert((next, next_seen.trailing_zeros())) {
+                        if visited.len() > PATTERN_SEARCH_LIMIT {
+                            return Pattern::Unknown;
+                        }
+                        que.push(Reverse(((next & !goals).count_ones(), next, next_seen)));
+                    }
+                }
+            }
+        }
+        Pattern::Deadlock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pattern_key, Pattern, PatternCell, PatternDatabase};
+    use PatternCell::{Box, BoxOnGoal, Floor, Goal, Player, Wall};
+
+    #[test]
+    fn test_pattern_db_0() {
+        // four boxes in a square, nothing can move
+        let key = pattern_key(2, 2, &[Box, Box, Box, BoxOnGoal]);
+        assert_eq!(PatternDatabase::learn(key), Pattern::Deadlock);
+        // all of them on goals is fine though
+        let key = pattern_key(2, 2, &[BoxOnGoal, BoxOnGoal, BoxOnGoal, BoxOnGoal]);
+        assert_eq!(PatternDatabase::learn(key), Pattern::Safe);
+    }
+
+    #[test]
+    fn test_pattern_db_1() {
+        // two boxes against a wall can't be separated, a third square lets them out
+        let key = pattern_key(2, 2, &[Wall, Wall, Box, Box]);
+        assert_eq!(PatternDatabase::learn(key), Pattern::Deadlock);
+        let key = pattern_key(2, 3, &[Wall, Wall, Floor, Box, Box, Player]);
+        assert_eq!(PatternDatabase::learn(key), Pattern::Deadlock);
+        let key = pattern_key(2, 3, &[Wall, Wall, Wall, Box, Floor, Box]);
+        assert_eq!(PatternDatabase::learn(key), Pattern::Safe);
+    }
+
+    #[test]
+    fn test_pattern_db_2() {
+        let path =
+            std::env::temp_dir().join(format!("sokoban_patterns_{}.txt", std::process::id()));
+        let mut db = PatternDatabase::new();
+        let deadlock = pattern_key(2, 2, &[Wall, Wall, Box, Box]);
+        let safe = pattern_key(1, 3, &[Box, Floor, Goal]);
+        assert_eq!(db.lookup(deadlock), Pattern::Deadlock);
+        assert_eq!(db.lookup(safe), Pattern::Safe);
+        db.save(&path).unwrap();
+        let loaded = PatternDatabase::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get(deadlock), Some(Pattern::Deadlock));
+        assert_eq!(loaded.get(safe), Some(Pattern::Safe));
+    }
+}
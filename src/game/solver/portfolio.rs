@@ -0,0 +1,220 @@
+use super::{
+    CancellationToken, DeadlockKind, SearchStrategy, Solution, Solver, SolverConfig, SolverError,
+};
+use crate::game::board::Board;
+use std::thread;
+
+/// When a portfolio stops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortfolioMode {
+    First, // the first solution wins
+    Best,  // the shortest solution wins, an optimal one ends the race early
+}
+
+/// The solution a portfolio settled on, and which configuration found it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortfolioResult {
+    pub solution: Solution,
+    pub winner: usize, // index into the configurations
+    pub config: SolverConfig,
+}
+
+/// A* with and without freeze checks, next to weighted A* for levels where optimal is out of reach.
+pub fn default_portfolio() -> Vec<SolverConfig> {
+    vec![
+        SolverConfig::default(),
+        SolverConfig {
+            deadlocks: vec![DeadlockKind::DeadSquares],
+            ..Default::default()
+        },
+        SolverConfig {
+            strategy: SearchStrategy::Weighted(300),
+            ..Default::default()
+        },
+    ]
+}
+
+/// Runs one solver per configuration on its own thread. Once the race is decided, the rest get cancelled through
+/// children of `cancel`. Finding no solution only decides it for a configuration that `is_exhaustive`, the others
+/// keep going. If no configuration finds a solution, an unsolvable level is reported over running out of some
+/// budget only if an exhaustive configuration found it so, since the others may have pruned the way to a solution.
+pub fn solve_portfolio(
+    g: &Board,
+    configs: &[SolverConfig],
+    mode: PortfolioMode,
+    cancel: &CancellationToken,
+) -> Result<PortfolioResult, SolverError> {
+    let race = cancel.child();
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    thread::scope(|scope| {
+        for (k, config) in configs.iter().enumerate() {
+            let (sender, race) = (sender.clone(), race.clone());
+            scope.spawn(move || {
+                let solver = Solver::with_config(g, config.clone());
+                let _ = sender.send((k, solver.solve(&race)));
+            });
+        }
+        drop(sender);
+        let mut best: Option<(usize, Solution)> = None;
+        let mut proof = None; // holds whatever the other configurations come up with
+        let mut error = None;
+        let mut unsolvable = false; // by a configuration that pruned more than dead boards
+        for (k, res) in receiver.iter() {
+            match res {
+                Ok(solution) => {
+                    // `optimal` is only set by a search that pruned nothing shorter away
+                    let done = mode == PortfolioMode::First || solution.optimal;
+                    // on a tie, the one known to be optimal says more
+                    if best.as_ref().is_none_or(|(_, v)| {
+                        (solution.seq.len(), !solution.optimal) < (v.seq.len(), !v.optimal)
+                    }) {
+                        best = Some((k, solution));
+                    }
+                    if done {
+                        race.cancel();
+                    }
+                }
+                Err(e @ SolverError::UnreachableBox { .. }) => {
+                    race.cancel();
+                    proof = Some(e);
+                }
+                Err(SolverError::Unsolvable) if configs[k].is_exhaustive() => {
+                    race.cancel();
+                    proof = Some(SolverError::Unsolvable);
+                }
+                Err(SolverError::Unsolvable) => {
+                    // the others may still get through what this one pruned
+                    unsolvable = true;
+                }
+                Err(e) => {
+                    error = error.or(Some(e));
+                }
+            }
+        }
+        match (best, proof.or(error)) {
+            (Some((winner, solution)), _) => Ok(PortfolioResult {
+                solution,
+                winner,
+                config: configs[winner].clone(),
+            }),
+            (None, Some(e)) => Err(e),
+            (None, None) if unsolvable => Err(SolverError::Unsolvable),
+            (None, None) => Err(SolverError::Cancelled),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_portfolio, solve_portfolio, PortfolioMode};
+    use crate::game::board::Board;
+    use crate::game::solver::{
+        test_level, CancellationToken, SearchStrategy, SolverConfig, SolverError, SolverLimits,
+    };
+
+    #[test]
+    fn test_portfolio_0() {
+        // A* finds the 44 moves, which ends the race early
+        let g = test_level("legacy/1");
+        let res = solve_portfolio(
+            &g,
+            &default_portfolio(),
+            PortfolioMode::Best,
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert!(res.solution.optimal);
+        assert_eq!(res.solution.seq.len(), 44);
+        assert_eq!(res.config, default_portfolio()[res.winner]);
+        let res = solve_portfolio(
+            &g,
+            &[SolverConfig {
+                strategy: SearchStrategy::Greedy,
+                ..Default::default()
+            }],
+            PortfolioMode::First,
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert_eq!(res.winner, 0);
+        assert!(!res.solution.optimal);
+    }
+
+    #[test]
+    fn test_portfolio_1() {
+        let g = Board::from(
+            "#####\n\
+             #@$.#\n\
+             #####",
+        );
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert_eq!(
+            solve_portfolio(&g, &default_portfolio(), PortfolioMode::First, &cancel),
+            Err(SolverError::Cancelled)
+        );
+        // the box is stuck in the corner, an exhaustive search finding out stops the others
+        let g = Board::from(
+            "######\n\
+             #@ .$#\n\
+             ######",
+        );
+        assert_eq!(
+            solve_portfolio(
+                &g,
+                &[
+                    SolverConfig::default(),
+                    SolverConfig {
+                        goal_rooms: false,
+                        ..Default::default()
+                    },
+                ],
+                PortfolioMode::First,
+                &CancellationToken::new()
+            ),
+            Err(SolverError::Unsolvable)
+        );
+    }
+
+    #[test]
+    fn test_portfolio_2() {
+        // the lower left goal is out of reach behind the pillar
+        let g = Board::from(
+            "#########\n\
+             #@      #\n\
+             #  $ $  #\n\
+             ##      #\n\
+             #. #   .#\n\
+             #########",
+        );
+        let limited = SolverConfig {
+            goal_rooms: false,
+            limits: SolverLimits {
+                max_states: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // goal room macros may have skipped the way, so running out of states is the honest answer
+        let res = solve_portfolio(
+            &g,
+            &[SolverConfig::default(), limited.clone()],
+            PortfolioMode::First,
+            &CancellationToken::new(),
+        );
+        assert!(matches!(res, Err(SolverError::LimitExceeded { .. })));
+        let exhaustive = SolverConfig {
+            goal_rooms: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            solve_portfolio(
+                &g,
+                &[SolverConfig::default(), limited, exhaustive],
+                PortfolioMode::First,
+                &CancellationToken::new()
+            ),
+            Err(SolverError::Unsolvable)
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use super::state_table::StateTable;
+use super::{CancellationToken, DeadlockKind, Solution, Solver, SolverConfig, SolverError};
+
+/// What an exhaustive search went through before running out of states.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Certificate {
+    pub states: usize, // push states left after tunnel and corral reductions
+    pub pruned: Vec<(DeadlockKind, usize)>, // how many boards each detector threw away
+    pub explored_hash: u64, // the same whatever order states were explored in
+    pub config: SolverConfig, // what the search ran with, sound pruning only
+}
+
+impl std::fmt::Display for Certificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Explored {} push states", self.states)?;
+        let reductions = [
+            (self.config.tunnels, "tunnel"),
+            (self.config.pi_corrals, "PI-corral"),
+        ]
+        .into_iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>();
+        if !reductions.is_empty() {
+            write!(f, " after {} reductions", reductions.join(" and "))?;
+        }
+        if !self.pruned.is_empty() {
+            let pruned = self
+                .pruned
+                .iter()
+                .map(|(kind, count)| format!("{} {}", kind, count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(f, ", pruned {}", pruned)?;
+        }
+        write!(f, ", hash {:016x}", self.explored_hash)
+    }
+}
+
+/// The outcome of `Solver::prove_unsolvable`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Unsolvable(Certificate),
+    Solvable(Solution),
+}
+
+/// Hashes every key in `visited`, summing up so that the order they were inserted in doesn't matter.
+pub(super) fn explored_hash(visited: &StateTable) -> u64 {
+    (0..visited.len())
+        .map(|idx| {
+            let mut h = visited
+                .key(idx)
+                .iter()
+                .fold(0xcbf2_9ce4_8422_2325_u64, |h, &v| {
+                    (h ^ v as u64).wrapping_mul(0x0000_0100_0000_01b3)
+                });
+            // spread the bits before adding up, close keys would otherwise cancel out
+            h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            h ^ (h >> 31)
+        })
+        .fold(0, u64::wrapping_add)
+}
+
+impl Solver<'_> {
+    /// Searches the push states reachable from the initial board, using only pruning that can't throw away a
+    /// solution: the deadlock detectors, and tunnels and PI-corrals if they are on, but no goal room macros, and
+    /// patterns are only learned during this search rather than read from a file. A box stopped halfway down a tunnel
+    /// can only be pushed on or back, so the tunnel macro loses nothing. While a PI-corral is there, only the pushes
+    /// into it are followed, and any solution has to make one of them before the boxes inside can reach their goals.
+    /// The states stay in memory. A box that can't reach any goal at all is reported as
+    /// `SolverError::UnreachableBox` before searching anything.
+    pub fn prove_unsolvable(&self, cancel: &CancellationToken) -> Result<Verdict, SolverError> {
+        let config = SolverConfig {
+            goal_rooms: false,
+            pattern_db: None,
+            external: None,
+            ..self.config.clone()
+        };
+        let mut solver = Solver::with_config(self.board, config.clone());
+        solver.progress = self.progress.clone();
+        let mut certificate = Certificate {
+            config,
+            ..Default::default()
+        };
+        let res = solver.search(
+            cancel,
+            solver.config.strategy,
+            usize::MAX,
+            solver.config.checkpoint.as_ref(),
+            Some(&mut certificate),
+        );
+        for detector in solver.detectors.iter() {
+            detector.finish();
+        }
+        match res {
+            Ok(solution) => Ok(Verdict::Solvable(solution)),
+            Err(SolverError::Unsolvable) => Ok(Verdict::Unsolvable(certificate)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Verdict;
+    use crate::game::board::Board;
+    use crate::game::solver::{
+        test_level, CancellationToken, DeadlockKind, SearchStrategy, Solver, SolverConfig,
+    };
+
+    #[test]
+    fn test_proof_0() {
+        let g = Board::from(
+            "#######\n\
+             #. @ .#\n\
+             # $#$ #\n\
+             #  #  #\n\
+             #######",
+        );
+        let Ok(Verdict::Unsolvable(certificate)) =
+            Solver::new(&g).prove_unsolvable(&CancellationToken::new())
+        else {
+            panic!("the level can't be solved");
+        };
+        assert!(certificate.states > 0);
+        assert!(!certificate.config.goal_rooms);
+        assert!(certificate.to_string().starts_with(&format!(
+            "Explored {} push states after tunnel reductions",
+            certificate.states
+        )));
+        // the explored set doesn't depend on which states got expanded first
+        let solver = Solver::with_config(
+            &g,
+            SolverConfig {
+                strategy: SearchStrategy::Greedy,
+                ..Default::default()
+            },
+        );
+        let Ok(Verdict::Unsolvable(greedy)) = solver.prove_unsolvable(&CancellationToken::new())
+        else {
+            panic!("the level can't be solved");
+        };
+        assert_eq!(greedy.states, certificate.states);
+        assert_eq!(greedy.explored_hash, certificate.explored_hash);
+        // without freeze checks there is more to explore
+        let solver = Solver::with_config(
+            &g,
+            SolverConfig {
+                deadlocks: vec![DeadlockKind::DeadSquares],
+                ..Default::default()
+            },
+        );
+        let Ok(Verdict::Unsolvable(fewer_checks)) =
+            solver.prove_unsolvable(&CancellationToken::new())
+        else {
+            panic!("the level can't be solved");
+        };
+        assert!(fewer_checks.states >= certificate.states);
+    }
+
+    #[test]
+    fn test_proof_1() {
+        let g = test_level("legacy/5");
+        // the proof only keeps track of the region the player can walk in, which doesn't give the fewest moves
+        let res = Solver::new(&g).prove_unsolvable(&CancellationToken::new());
+        assert!(matches!(res, Ok(Verdict::Solvable(solution)) if !solution.optimal));
+    }
+}
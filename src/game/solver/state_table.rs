@@ -0,0 +1,127 @@
+/// Visited set for the solver, storing every state as a fixed width run of `u16`s (the sorted floor indices of all
+/// boxes followed by the normalized player square) in one flat buffer. Lookups go through an open-addressing table of
+/// `u32` entry indices with linear probing, so a state costs `2 * stride` bytes plus a few bytes of slot overhead.
+pub struct StateTable {
+    stride: usize,
+    keys: Vec<u16>,  // entry `k` lives in `keys[k * stride..(k + 1) * stride]`
+    slots: Vec<u32>, // 0 marks an empty slot, otherwise entry index + 1
+}
+
+impl StateTable {
+    const INIT_SLOTS: usize = 1 << 10;
+
+    pub fn new(stride: usize) -> Self {
+        Self {
+            stride,
+            keys: vec![],
+            slots: vec![0; Self::INIT_SLOTS],
+        }
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len() / self.stride.max(1)
+    }
+
+    /// Bytes held by the table, allocated but unused capacity included.
+    pub fn memory_usage(&self) -> usize {
+        self.keys.capacity() * std::mem::size_of::<u16>()
+            + self.slots.capacity() * std::mem::size_of::<u32>()
+    }
+
+    #[inline]
+    pub fn key(&self, idx: usize) -> &[u16] {
+        &self.keys[idx * self.stride..(idx + 1) * self.stride]
+    }
+
+    #[inline]
+    fn hash(key: &[u16]) -> u64 {
+        let mut h = 0xcbf2_9ce4_8422_2325_u64;
+        for &v in key {
+            h = (h ^ v as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        // the low bits pick the slot, fold the well mixed high bits into them
+        h ^ (h >> 29)
+    }
+
+    #[inline]
+    fn find_slot(&self, key: &[u16]) -> (usize, Option<usize>) {
+        // returns (slot, Some(entry index)) if found, otherwise the empty slot the key would go into
+        let mask = self.slots.len() - 1;
+        let mut slot = Self::hash(key) as usize & mask;
+        loop {
+            match self.slots[slot] {
+                0 => return (slot, None),
+                v if self.key(v as usize - 1) == key => return (slot, Some(v as usize - 1)),
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    pub fn get(&self, key: &[u16]) -> Option<usize> {
+        self.find_slot(key).1
+    }
+
+    #[inline]
+    pub fn contains(&self, key: &[u16]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Inserts `key` if it isn't present yet. Returns the entry index and whether it was newly inserted.
+    pub fn insert(&mut self, key: &[u16]) -> (usize, bool) {
+        debug_assert_eq!(key.len(), self.stride);
+        match self.find_slot(key) {
+            (_, Some(idx)) => (idx, false),
+            (slot, None) => {
+                let idx = self.len();
+                self.keys.extend_from_slice(key);
+                self.slots[slot] = idx as u32 + 1;
+                if (idx + 1) * 4 >= self.slots.len() * 3 {
+                    self.grow();
+                }
+                (idx, true)
+            }
+        }
+    }
+
+    fn grow(&mut self) {
+        self.slots = vec![0; self.slots.len() * 2];
+        for idx in 0..self.len() {
+            let (slot, _) = self.find_slot(self.key(idx));
+            self.slots[slot] = idx as u32 + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StateTable;
+
+    #[test]
+    fn test_state_table_0() {
+        let mut table = StateTable::new(3);
+        assert_eq!(table.insert(&[1, 2, 3]), (0, true));
+        assert_eq!(table.insert(&[1, 2, 4]), (1, true));
+        assert_eq!(table.insert(&[1, 2, 3]), (0, false));
+        assert_eq!(table.get(&[1, 2, 4]), Some(1));
+        assert!(!table.contains(&[4, 2, 1]));
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn test_state_table_1() {
+        // survives growing past the initial slot count
+        let mut table = StateTable::new(2);
+        for i in 0..5000u16 {
+            assert_eq!(table.insert(&[i, i / 7]), (i as usize, true));
+        }
+        for i in 0..5000u16 {
+            assert_eq!(table.get(&[i, i / 7]), Some(i as usize));
+        }
+        assert!(!table.contains(&[5000, 0]));
+    }
+}
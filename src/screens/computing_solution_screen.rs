@@ -1,15 +1,18 @@
 use super::screen::{Screen, ScreenTransition};
-use super::solver_screen::SolverScreen;
-use crossterm::cursor::MoveTo;
+use super::solver_screen::{describe_error, SolverScreen};
+use crossbeam_channel::Receiver;
+use crossterm::cursor::{MoveTo, MoveToNextLine};
 use crossterm::event::{Event, KeyCode, KeyEvent};
 use crossterm::style::{PrintStyledContent, Stylize};
 use crossterm::terminal::{Clear, ClearType};
 use crossterm::{execute, queue};
+use sokoban::game::board::Board;
+use sokoban::game::solver::{CancellationToken, Progress, Solution, SolverError};
 use sokoban::utils::print_by_queue::PrintFullByQueue;
 use std::cell::RefCell;
 use std::io::stdout;
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::thread;
 
@@ -20,19 +23,73 @@ enum Status {
 }
 
 pub struct ComputingSolutionScreen {
-    pub sender: Sender<()>,
+    pub cancel: CancellationToken,
     pub handle: Option<thread::JoinHandle<Arc<SolverScreen>>>,
+    progress: Receiver<Progress>,
+    latest: Option<Progress>,
+    improvements: Option<(Board, Receiver<Solution>)>,
+    checkpoint: Option<PathBuf>, // where the search keeps saving itself
     status: Status,
 }
 
 impl ComputingSolutionScreen {
-    pub fn new(sender: Sender<()>, handle: thread::JoinHandle<Arc<SolverScreen>>) -> Self {
+    pub fn new(
+        cancel: CancellationToken,
+        progress: Receiver<Progress>,
+        handle: thread::JoinHandle<Arc<SolverScreen>>,
+    ) -> Self {
         Self {
-            sender,
+            cancel,
             handle: Some(handle),
+            progress,
+            latest: None,
+            improvements: None,
+            checkpoint: None,
             status: Status::Computing,
         }
     }
+
+    /// For an anytime search: hands over to a `SolverScreen` as soon as the first solution for `g` comes in.
+    pub fn with_improvements(mut self, g: Board, improvements: Receiver<Solution>) -> Self {
+        self.improvements = Some((g, improvements));
+        self
+    }
+
+    /// Tells where the search saves itself to, so that cancelling it is not the end of it.
+    pub fn with_checkpoint(mut self, path: PathBuf) -> Self {
+        self.checkpoint = Some(path);
+        self
+    }
+
+    fn print_progress(&self) -> Result<(), std::io::Error> {
+        let Some(progress) = &self.latest else {
+            return Ok(());
+        };
+        queue!(
+            stdout(),
+            MoveTo(0, 1),
+            Clear(ClearType::CurrentLine),
+            PrintStyledContent(
+                format!(
+                    "{:.1?} elapsed, expanded {} states ({:.0}/s), {} in frontier, f-bound {}",
+                    progress.stats.elapsed,
+                    progress.stats.expanded,
+                    progress.states_per_sec,
+                    progress.stats.frontier,
+                    progress.f_bound
+                )
+                .dark_grey()
+            ),
+            MoveTo(0, 3)
+        )?;
+        for row in progress.board.cells.iter() {
+            for cell in row.iter() {
+                cell.print_full()?;
+            }
+            queue!(stdout(), MoveToNextLine(1))?;
+        }
+        Ok(())
+    }
 }
 
 impl PrintFullByQueue for ComputingSolutionScreen {
@@ -40,12 +97,20 @@ impl PrintFullByQueue for ComputingSolutionScreen {
         queue!(
             stdout(),
             MoveTo(0, 0),
-            PrintStyledContent(
-                "Computing optimal solution, press <q> to cancel"
-                    .grey()
-                    .italic()
-            )
-        )
+            PrintStyledContent("Computing solution, press <q> to cancel".grey().italic())
+        )?;
+        if let Some(path) = &self.checkpoint {
+            queue!(
+                stdout(),
+                MoveTo(0, 2),
+                PrintStyledContent(
+                    format!("Saving progress to {}", path.display())
+                        .dark_grey()
+                        .italic()
+                )
+            )?;
+        }
+        self.print_progress()
     }
 }
 
@@ -53,11 +118,40 @@ impl Screen for ComputingSolutionScreen {
     fn update(&mut self, event: Option<Event>) -> ScreenTransition {
         match self.status {
             Status::Computing => {
+                if let Some(progress) = self.progress.try_iter().last() {
+                    self.latest = Some(progress);
+                    let _ = self.print_progress();
+                }
+                if let Some((g, improvements)) = &self.improvements {
+                    if let Ok(solution) = improvements.try_recv() {
+                        // the search keeps going in the background, the solver screen picks up what it finds next
+                        let screen = SolverScreen::new(g.clone(), Ok(solution))
+                            .with_search(improvements.clone(), self.cancel.clone());
+                        self.handle = None;
+                        self.status = Status::Ok;
+                        return ScreenTransition::SwitchTo(Rc::new(RefCell::new(screen)));
+                    }
+                }
                 if let Some(handle) = &self.handle {
                     if handle.is_finished() {
                         if let Some(handle) = self.handle.take() {
                             let res = handle.join();
                             match res {
+                                Ok(arc_screen) if arc_screen.sol == Err(SolverError::Cancelled) => {
+                                    // nothing to show on the board, somebody else stopped the search
+                                    let _ = queue!(
+                                        stdout(),
+                                        MoveTo(0, 0),
+                                        Clear(ClearType::CurrentLine),
+                                        PrintStyledContent(describe_error(&SolverError::Cancelled)),
+                                        MoveToNextLine(1),
+                                        Clear(ClearType::CurrentLine),
+                                        PrintStyledContent(
+                                            "Press <q> to return to the game".dark_grey().italic()
+                                        )
+                                    );
+                                    self.status = Status::Err;
+                                }
                                 Ok(arc_screen) => {
                                     let screen = arc_screen.as_ref().clone();
                                     self.status = Status::Ok;
@@ -90,7 +184,7 @@ impl Screen for ComputingSolutionScreen {
                 ..
             })) => {
                 if let Status::Computing = self.status {
-                    let _ = self.sender.send(());
+                    self.cancel.cancel();
                     if let Some(handle) = self.handle.take() {
                         let _ = execute!(
                             stdout(),
@@ -1,34 +1,385 @@
 use super::computing_solution_screen::ComputingSolutionScreen;
 use super::screen::{Screen, ScreenTransition};
 use super::solver_screen::SolverScreen;
+use crossbeam_channel::{Receiver, TryRecvError};
 use crossterm::cursor::{MoveTo, MoveToNextLine};
 use crossterm::event::{Event, KeyCode, KeyEvent};
 use crossterm::queue;
-use crossterm::style::Print;
+use crossterm::style::{Print, PrintStyledContent, Stylize};
 use crossterm::terminal::Clear;
 use sokoban::game::board::Board;
 use sokoban::game::board_event::BoardEvent;
-use sokoban::game::solver::Solver;
+use sokoban::game::entity::Entity;
+use sokoban::game::solver::portfolio::{default_portfolio, solve_portfolio, PortfolioMode};
+use sokoban::game::solver::{
+    CancellationToken, Checkpointing, DeadlockExplanation, DeadlockKind, SearchStrategy, Solver,
+    SolverConfig, SolverError, SolverLimits, Verdict,
+};
 use sokoban::utils::print_by_queue::PrintFullByQueue;
 use std::cell::RefCell;
 use std::io::stdout;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::{mpsc, Arc};
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
+
+/// How many states the live check may expand before settling on `Solvability::Unknown`.
+const LIVE_CHECK_STATES: usize = 20_000;
+
+/// Where cancelled searches are saved, one file per level and mode.
+const CHECKPOINT_DIR: &str = "checkpoints";
+
+/// What pressing `o` starts.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SolveMode {
+    Single,
+    Anytime,   // keep searching for shorter solutions after the first one
+    Portfolio, // race several configurations, see `default_portfolio`
+    Prove,     // search everything with sound pruning only, see `Solver::prove_unsolvable`
+    Pareto,    // every best trade-off between moves and pushes, see `Solver::solve_pareto`
+}
+
+/// What the live check found out about the board as it is now.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Solvability {
+    Solvable,
+    Unknown, // ran out of budget
+    Dead,    // a search without unsound pruning found no solution
+}
+
+/// What the overlay and the deadlock warning show for one position, worked out once per position.
+#[derive(Clone, Default)]
+struct Inspection {
+    boxes: Vec<(usize, usize)>, // the position this is about, along with the player square
+    player: (usize, usize),
+    closed_diagonal: Option<(usize, usize)>,
+    overlay: bool, // whether the rest has been worked out, it only shows with the overlay on
+    dead_squares: Vec<(usize, usize)>,
+    frozen_boxes: Vec<(usize, usize)>,
+    explanation: Option<DeadlockExplanation>,
+}
+
+/// A solver going over the board in the background, see `BoardScreen::with_live_check`.
+#[derive(Clone)]
+struct LiveCheck {
+    cancel: CancellationToken,
+    result: Receiver<Solvability>,
+}
 
 #[derive(Clone)]
 pub struct BoardScreen {
     pub g: Board,
+    level: String, // names the checkpoint files
+    pub config: SolverConfig,
+    pub mode: SolveMode,
+    overlay: bool,  // tint dead squares, highlight frozen boxes and explain deadlocks
+    warnings: bool, // off while a solution is played back, which never runs into a deadlock
+    inspection: Inspection, // for the board as it is now
+    live: bool,
+    check: Option<LiveCheck>,         // still running for the current board
+    solvability: Option<Solvability>, // `None` until the first check comes back
 }
 
 impl BoardScreen {
     pub fn new(g: Board) -> Self {
-        Self { g }
+        let mut screen = Self {
+            g,
+            level: "level".to_string(),
+            config: SolverConfig {
+                pattern_db: Some(PathBuf::from("deadlock_patterns.txt")),
+                // rather give up with some statistics than spin until the user does
+                limits: SolverLimits {
+                    timeout: Some(Duration::from_secs(300)),
+                    max_memory: Some(4 << 30),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            mode: SolveMode::Single,
+            overlay: false,
+            warnings: true,
+            inspection: Inspection::default(),
+            live: false,
+            check: None,
+            solvability: None,
+        };
+        screen.inspect();
+        screen
+    }
+
+    /// Names the checkpoints after `name`, the path the level was loaded from.
+    pub fn with_level_name(mut self, name: &str) -> Self {
+        self.level = Path::new(name)
+            .with_extension("")
+            .iter()
+            .map(|v| v.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("-");
+        self
+    }
+
+    /// Where a cancelled search in the current mode goes on from next time. Only a single search and a proof pick
+    /// up where they left off.
+    fn checkpoint(&self) -> Option<Checkpointing> {
+        let mode = match self.mode {
+            SolveMode::Single => "single",
+            SolveMode::Prove => "prove",
+            _ => return None,
+        };
+        Some(Checkpointing {
+            path: Path::new(CHECKPOINT_DIR).join(format!("{}-{}.bin", self.level, mode)),
+            interval: Duration::from_secs(30),
+        })
+    }
+
+    /// Leaves out the deadlock warning under the board, for playing back a solution.
+    pub fn without_warnings(mut self) -> Self {
+        self.warnings = false;
+        self
+    }
+
+    /// Re-checks whether the level can still be solved after every push, with a solver limited to
+    /// `LIVE_CHECK_STATES` in a background thread. A check still running when the player pushes again is cancelled.
+    pub fn with_live_check(mut self) -> Self {
+        self.live = true;
+        self
+    }
+
+    fn start_check(&mut self) {
+        self.cancel_check();
+        // only sound pruning, a dead board has to be dead for real
+        let config = SolverConfig {
+            deadlocks: self.config.deadlocks.clone(),
+            pi_corrals: false,
+            goal_rooms: false,
+            strategy: SearchStrategy::Greedy,
+            limits: SolverLimits {
+                max_states: Some(LIVE_CHECK_STATES),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let g = self.g.clone();
+        let cancel = CancellationToken::new();
+        let worker_cancel = cancel.clone();
+        let (sender, result) = crossbeam_channel::bounded(1);
+        thread::spawn(move || {
+            let exhaustive = config.is_exhaustive();
+            let res = Solver::with_config(&g, config).solve(&worker_cancel);
+            let _ = sender.send(match res {
+                Ok(_) => Solvability::Solvable,
+                Err(SolverError::UnreachableBox { .. }) => Solvability::Dead,
+                Err(SolverError::Unsolvable) if exhaustive => Solvability::Dead,
+                // running into `LIVE_CHECK_STATES` says nothing either way
+                Err(_) => Solvability::Unknown,
+            });
+        });
+        self.check = Some(LiveCheck { cancel, result });
+        self.solvability = None;
+    }
+
+    fn cancel_check(&mut self) {
+        if let Some(check) = self.check.take() {
+            check.cancel.cancel();
+        }
+    }
+
+    fn poll_check(&mut self) {
+        let Some(check) = &self.check else {
+            return;
+        };
+        self.solvability = match check.result.try_recv() {
+            Ok(solvability) => Some(solvability),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Some(Solvability::Unknown),
+        };
+        self.check = None;
+        let _ = self.print_solvability();
+    }
+
+    fn print_solvability(&self) -> Result<(), std::io::Error> {
+        if !self.live {
+            return Ok(());
+        }
+        queue!(
+            stdout(),
+            MoveTo(0, self.g.n as u16 + 2),
+            Clear(crossterm::terminal::ClearType::CurrentLine),
+            PrintStyledContent(match self.solvability {
+                Some(Solvability::Solvable) => "● Solvable".green(),
+                Some(Solvability::Unknown) => "● Unknown, the check ran out of budget".yellow(),
+                Some(Solvability::Dead) => "● Dead, no solution from here".red().bold(),
+                None => "● Checking...".dark_grey(),
+            })
+        )
+    }
+
+    fn print_config(&self) -> Result<(), std::io::Error> {
+        let enabled = DeadlockKind::ALL
+            .iter()
+            .enumerate()
+            .map(|(k, kind)| match self.config.deadlocks.contains(kind) {
+                true => format!("[{}] {}", k + 1, kind),
+                false => format!(" {}  {}", k + 1, kind),
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        queue!(
+            stdout(),
+            MoveTo(0, self.g.n as u16),
+            Clear(crossterm::terminal::ClearType::CurrentLine),
+            PrintStyledContent(
+                format!(
+                    "Deadlock checks: {}  <w> search: {}  <a>/<p>/<u>/<f> mode: {}  <d> overlay: {}",
+                    enabled,
+                    self.config.strategy,
+                    match self.mode {
+                        SolveMode::Single => "single",
+                        SolveMode::Anytime => "anytime",
+                        SolveMode::Portfolio => "portfolio",
+                        SolveMode::Prove => "prove unsolvable",
+                        SolveMode::Pareto => "moves/pushes front",
+                    },
+                    match self.overlay {
+                        true => "on",
+                        false => "off",
+                    }
+                )
+                .dark_grey()
+                .italic()
+            )
+        )
     }
 }
 
-impl PrintFullByQueue for BoardScreen {
-    fn print_full(&self) -> Result<(), std::io::Error> {
+impl BoardScreen {
+    /// Works out the deadlocks on the board again, but only once the position has changed or the overlay needs
+    /// more than there is.
+    fn inspect(&mut self) {
+        if !self.warnings && !self.overlay {
+            return;
+        }
+        let boxes = (0..self.g.n)
+            .flat_map(|i| (0..self.g.cells[i].len()).map(move |j| (i, j)))
+            .filter(|&(i, j)| self.g.cells[i][j].entity == Some(Entity::Box))
+            .collect::<Vec<_>>();
+        let player = (self.g.i, self.g.j);
+        if self.inspection.boxes == boxes
+            && self.inspection.player == player
+            && (self.inspection.overlay || !self.overlay)
+        {
+            return;
+        }
+        // only the board itself is needed, skip everything the search would set up
+        let solver = Solver::with_config(
+            &self.g,
+            SolverConfig {
+                deadlocks: vec![],
+                pi_corrals: false,
+                tunnels: false,
+                goal_rooms: false,
+                ..Default::default()
+            },
+        );
+        let mut inspection = Inspection {
+            boxes,
+            player,
+            closed_diagonal: solver.find_closed_diagonal(),
+            overlay: self.overlay,
+            ..Default::default()
+        };
+        if self.overlay {
+            inspection.dead_squares = solver.dead_squares();
+            inspection.frozen_boxes = solver.frozen_boxes();
+            inspection.explanation = solver.explain_deadlock();
+        }
+        self.inspection = inspection;
+    }
+
+    fn print_deadlock_warning(&self) -> Result<(), std::io::Error> {
+        if !self.warnings {
+            return Ok(());
+        }
+        let inspection = &self.inspection;
+        queue!(
+            stdout(),
+            MoveTo(0, self.g.n as u16 + 1),
+            Clear(crossterm::terminal::ClearType::CurrentLine)
+        )?;
+        if self.overlay {
+            let Some(explanation) = &inspection.explanation else {
+                return Ok(());
+            };
+            let why = match explanation.kind {
+                DeadlockKind::DeadSquares => "no goal can be reached from a red square",
+                DeadlockKind::Freeze => "the boxes in red can never move again, off their goals",
+                DeadlockKind::ClosedDiagonal => {
+                    "the boxes in red close in an empty square, the first one pushed in freezes"
+                }
+                DeadlockKind::Corral => {
+                    "the boxes in red fence off an area that can never be opened up again"
+                }
+                kind => kind.name(),
+            };
+            return queue!(
+                stdout(),
+                PrintStyledContent(
+                    format!("Deadlock ({}): {}", explanation.kind, why)
+                        .red()
+                        .bold()
+                )
+            );
+        }
+        if let Some((i, j)) = inspection.closed_diagonal {
+            queue!(
+                stdout(),
+                PrintStyledContent(
+                    format!(
+                        "Deadlock: the square at row {}, column {} is closed in for good",
+                        i + 1,
+                        j + 1
+                    )
+                    .red()
+                    .bold()
+                )
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_overlay(&self) -> Result<(), std::io::Error> {
+        if !self.overlay {
+            return Ok(());
+        }
+        let inspection = &self.inspection;
+        for &(i, j) in inspection.dead_squares.iter() {
+            let cell = self.g.cells[i][j];
+            queue!(
+                stdout(),
+                MoveTo(j as u16, i as u16),
+                PrintStyledContent(format!("{:?}", cell).on_dark_red())
+            )?;
+        }
+        let blamed = inspection
+            .explanation
+            .as_ref()
+            .map_or(&[][..], |v| &v.boxes[..]);
+        for &(i, j) in inspection.frozen_boxes.iter().chain(blamed) {
+            let text = format!("{:?}", self.g.cells[i][j]);
+            queue!(
+                stdout(),
+                MoveTo(j as u16, i as u16),
+                PrintStyledContent(match blamed.contains(&(i, j)) {
+                    true => text.white().on_red(),
+                    false => text.black().on_yellow(),
+                })
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn print_board(&self) -> Result<(), std::io::Error> {
         queue!(
             stdout(),
             Clear(crossterm::terminal::ClearType::All),
@@ -44,28 +395,156 @@ impl PrintFullByQueue for BoardScreen {
     }
 }
 
+impl PrintFullByQueue for BoardScreen {
+    fn print_full(&self) -> Result<(), std::io::Error> {
+        self.print_board()?;
+        self.print_overlay()?;
+        self.print_config()?;
+        self.print_deadlock_warning()?;
+        self.print_solvability()
+    }
+}
+
 impl Screen for BoardScreen {
     fn update(&mut self, event: Option<Event>) -> ScreenTransition {
+        if self.live && self.check.is_none() && self.solvability.is_none() {
+            self.start_check();
+            let _ = self.print_solvability();
+        }
+        self.poll_check();
         match event {
             Some(Event::Key(KeyEvent {
                 code: KeyCode::Char('o'),
                 ..
             })) => {
-                let (sender, receiver) = mpsc::channel();
+                let cancel = CancellationToken::new();
+                let (progress_sender, progress_receiver) = crossbeam_channel::unbounded();
                 let g = self.g.clone();
+                let config = SolverConfig {
+                    checkpoint: self.checkpoint(),
+                    ..self.config.clone()
+                };
+                let checkpoint = config.checkpoint.as_ref().map(|v| v.path.clone());
+                let (improvement_sender, improvement_receiver) = crossbeam_channel::unbounded();
+                let mode = self.mode;
+                let worker_cancel = cancel.clone();
                 let handle = thread::spawn(move || {
-                    let solver = Solver::new(&g);
-                    let solution = solver.solve(Some(receiver));
+                    if mode == SolveMode::Portfolio {
+                        // the racers share the budget and pattern file picked for the game
+                        let configs = default_portfolio()
+                            .into_iter()
+                            .map(|v| SolverConfig {
+                                pattern_db: config.pattern_db.clone(),
+                                limits: config.limits,
+                                ..v
+                            })
+                            .collect::<Vec<_>>();
+                        let res =
+                            solve_portfolio(&g, &configs, PortfolioMode::First, &worker_cancel);
+                        return Arc::new(match res {
+                            Ok(res) => SolverScreen::new(g, Ok(res.solution))
+                                .with_note(format!("Found first by {}", res.config)),
+                            Err(e) => SolverScreen::new(g, Err(e)),
+                        });
+                    }
+                    let solver = Solver::with_config(&g, config)
+                        .with_progress(progress_sender, Duration::from_millis(200));
+                    let solution = match mode {
+                        SolveMode::Anytime => {
+                            solver.solve_anytime(&worker_cancel, &improvement_sender)
+                        }
+                        SolveMode::Prove => match solver.prove_unsolvable(&worker_cancel) {
+                            Ok(Verdict::Solvable(solution)) => Ok(solution),
+                            Ok(Verdict::Unsolvable(certificate)) => {
+                                return Arc::new(
+                                    SolverScreen::new(g, Err(SolverError::Unsolvable))
+                                        .with_note(certificate.to_string()),
+                                );
+                            }
+                            Err(e) => Err(e),
+                        },
+                        SolveMode::Pareto => match solver.solve_pareto(&worker_cancel) {
+                            Ok(front) => {
+                                let first = Ok(front[0].solution.clone());
+                                return Arc::new(SolverScreen::new(g, first).with_front(front));
+                            }
+                            Err(e) => Err(e),
+                        },
+                        _ => solver.solve(&worker_cancel),
+                    };
                     Arc::new(SolverScreen::new(g, solution))
                 });
-                ScreenTransition::SwitchTo(Rc::new(RefCell::new(ComputingSolutionScreen::new(
-                    sender, handle,
-                ))))
+                let mut screen = ComputingSolutionScreen::new(cancel, progress_receiver, handle);
+                if mode == SolveMode::Anytime {
+                    screen = screen.with_improvements(self.g.clone(), improvement_receiver);
+                }
+                if let Some(path) = checkpoint {
+                    screen = screen.with_checkpoint(path);
+                }
+                ScreenTransition::SwitchTo(Rc::new(RefCell::new(screen)))
+            }
+            Some(Event::Key(KeyEvent {
+                code: KeyCode::Char(c @ ('a' | 'p' | 'u' | 'f')),
+                ..
+            })) => {
+                let mode = match c {
+                    'a' => SolveMode::Anytime,
+                    'p' => SolveMode::Portfolio,
+                    'u' => SolveMode::Prove,
+                    _ => SolveMode::Pareto,
+                };
+                self.mode = match self.mode == mode {
+                    true => SolveMode::Single,
+                    false => mode,
+                };
+                let _ = self.print_config();
+                ScreenTransition::Continue
+            }
+            Some(Event::Key(KeyEvent {
+                code: KeyCode::Char('d'),
+                ..
+            })) => {
+                self.overlay ^= true;
+                self.inspect();
+                let _ = self.print_full();
+                ScreenTransition::Continue
+            }
+            Some(Event::Key(KeyEvent {
+                code: KeyCode::Char('w'),
+                ..
+            })) => {
+                self.config.strategy = match self.config.strategy {
+                    SearchStrategy::AStar => SearchStrategy::Weighted(200),
+                    SearchStrategy::Weighted(percent) if percent < 500 => {
+                        SearchStrategy::Weighted(500)
+                    }
+                    SearchStrategy::Weighted(_) => SearchStrategy::Greedy,
+                    SearchStrategy::Greedy => SearchStrategy::AStar,
+                };
+                let _ = self.print_config();
+                ScreenTransition::Continue
+            }
+            Some(Event::Key(KeyEvent {
+                code: KeyCode::Char(c @ '1'..='6'),
+                ..
+            })) => {
+                let kind = DeadlockKind::ALL[c as usize - '1' as usize];
+                match self.config.deadlocks.iter().position(|v| *v == kind) {
+                    Some(k) => {
+                        self.config.deadlocks.remove(k);
+                    }
+                    None => self.config.deadlocks.push(kind),
+                }
+                let _ = self.print_config();
+                ScreenTransition::Continue
             }
             Some(Event::Key(KeyEvent {
                 code: KeyCode::Char('q'),
                 ..
-            })) => ScreenTransition::Back,
+            })) => {
+                self.cancel_check();
+                ScreenTransition::Back
+            }
             Some(event) => {
                 // TODO refactor execute to not return screen transition
                 let events = self.g.execute(event.into());
@@ -85,6 +564,19 @@ impl Screen for BoardScreen {
                         }
                     }
                 }
+                if !events.is_empty() {
+                    self.inspect();
+                    let _ = self.print_overlay();
+                    let _ = self.print_deadlock_warning();
+                }
+                // only a push puts down a box
+                let pushed = events.iter().any(|event| {
+                    matches!(event, BoardEvent::Put(_, _, cell) if cell.entity == Some(Entity::Box))
+                });
+                if self.live && pushed {
+                    self.start_check();
+                    let _ = self.print_solvability();
+                }
                 ScreenTransition::Continue
             }
             None => ScreenTransition::Continue,
@@ -41,7 +41,12 @@ impl From<Vec<FileLevel>> for LevelSelectorScreen {
                 let filename = file_level.filename;
                 let loader: LevelLoader = Box::new(move || {
                     load_file(filename.clone())
-                        .map(|val| Rc::new(RefCell::new(BoardScreen::new(val.as_str().into()))))
+                        .map(|val| {
+                            BoardScreen::new(val.as_str().into())
+                                .with_level_name(&filename)
+                                .with_live_check()
+                        })
+                        .map(|screen| Rc::new(RefCell::new(screen)))
                         .map_err(|err| err.to_string())
                 });
                 (file_level.level_name, loader)
@@ -1,12 +1,17 @@
 use super::game_screen::BoardScreen;
 use super::screen::{Screen, ScreenTransition};
+use crossbeam_channel::{Receiver, TryRecvError};
 use crossterm::cursor::{MoveTo, MoveToNextLine};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
 use crossterm::queue;
-use crossterm::style::{style, PrintStyledContent, Stylize};
+use crossterm::style::{style, PrintStyledContent, StyledContent, Stylize};
 use crossterm::terminal::{Clear, ClearType};
 use sokoban::{
-    game::{board::Board, board_command::BoardCommand, solver::Solution},
+    game::{
+        board::Board,
+        board_command::BoardCommand,
+        solver::{breakdown::Segment, CancellationToken, FrontPoint, Solution, SolverError},
+    },
     utils::print_by_queue::PrintFullByQueue,
 };
 use std::io::stdout;
@@ -15,30 +20,223 @@ use std::io::stdout;
 pub struct SolverScreen {
     pub origin_game: Board,
     pub game_screen: BoardScreen,
-    pub sol: Result<Solution, String>,
+    pub sol: Result<Solution, SolverError>,
     pub cur: usize,
     pub play: bool,
     print_per_n_updates: u8,
     cur_update: u8,
+    improvements: Option<Receiver<Solution>>, // shorter solutions from a search still running in the background
+    pending: Option<Solution>, // the best one of them, waiting for playback to restart
+    cancel: Option<CancellationToken>,
+    note: Option<String>, // anything else worth knowing about how the solution was found
+    front: Vec<FrontPoint>, // moves against pushes, `sol` being the one picked
+    picked: usize,
+    by_push: bool, // <space> plays up to the next box instead of starting playback
+    segments: Vec<Segment>, // `sol` broken down by push
+    step: Option<String>, // what the last step by push did
 }
 
 impl SolverScreen {
-    pub fn new(game: Board, sol: Result<Solution, String>) -> Self {
+    pub fn new(game: Board, sol: Result<Solution, SolverError>) -> Self {
+        let segments = sol.as_ref().map_or(vec![], |solution| {
+            solution.breakdown(&game).unwrap_or_default()
+        });
         Self {
             origin_game: game.clone(),
-            game_screen: BoardScreen::new(game),
+            game_screen: BoardScreen::new(game).without_warnings(),
             sol,
             cur: 0,
             play: false,
             print_per_n_updates: 16,
             cur_update: 0,
+            improvements: None,
+            pending: None,
+            cancel: None,
+            note: None,
+            front: vec![],
+            picked: 0,
+            by_push: false,
+            segments,
+            step: None,
         }
     }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.note = Some(note);
+        self
+    }
+
+    /// Lets the user pick any point of `front` to play back, starting with the first one.
+    pub fn with_front(mut self, front: Vec<FrontPoint>) -> Self {
+        if let Some(point) = front.first() {
+            self.set_sol(point.solution.clone());
+        }
+        self.front = front;
+        self.picked = 0;
+        self
+    }
+
+    /// Keeps picking up shorter solutions from `improvements` until the search behind it ends or `cancel` is
+    /// cancelled on leaving the screen.
+    pub fn with_search(
+        mut self,
+        improvements: Receiver<Solution>,
+        cancel: CancellationToken,
+    ) -> Self {
+        self.improvements = Some(improvements);
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn poll_improvements(&mut self) {
+        let Some(improvements) = &self.improvements else {
+            return;
+        };
+        let mut changed = false;
+        let mut finished = false;
+        loop {
+            match improvements.try_recv() {
+                Ok(solution) => {
+                    match &mut self.sol {
+                        // the one being played, now proven optimal
+                        Ok(cur) if self.pending.is_none() && cur.seq == solution.seq => {
+                            *cur = solution
+                        }
+                        _ => self.pending = Some(solution),
+                    }
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        if finished {
+            self.improvements = None;
+            changed = true;
+        }
+        if self.cur == 0 && !self.play {
+            // nothing played yet, switch right away
+            self.adopt_pending();
+        }
+        if changed {
+            let _ = queue!(stdout(), Clear(ClearType::All));
+            let _ = self.print_full();
+        }
+    }
+
+    fn adopt_pending(&mut self) {
+        if let Some(solution) = self.pending.take() {
+            self.set_sol(solution);
+        }
+    }
+
+    fn set_sol(&mut self, solution: Solution) {
+        self.segments = solution.breakdown(&self.origin_game).unwrap_or_default();
+        self.sol = Ok(solution);
+    }
+
+    fn restart(&mut self) {
+        self.cur = 0;
+        self.cur_update = 0;
+        self.step = None;
+        self.game_screen.g = self.origin_game.clone();
+        let _ = queue!(stdout(), Clear(ClearType::All));
+        let _ = self.print_full();
+    }
+
+    /// Plays the rest of the walk to the next box and every push of it, see `Solution::breakdown`.
+    fn step_by_push(&mut self) {
+        let Ok(solution) = &self.sol else {
+            return;
+        };
+        let mut end = 0;
+        let Some(segment) = self.segments.iter().find(|segment| {
+            end += segment.len();
+            end > self.cur
+        }) else {
+            return;
+        };
+        while self.cur < end {
+            self.game_screen
+                .update(Some(command_event(solution.seq[self.cur])));
+            self.cur += 1;
+        }
+        self.step = Some(segment.to_string());
+        let _ = queue!(stdout(), Clear(ClearType::All));
+        let _ = self.print_full();
+    }
+
+    fn print_front(&self) -> Result<(), std::io::Error> {
+        if self.front.is_empty() {
+            return Ok(());
+        }
+        queue!(
+            stdout(),
+            MoveToNextLine(1),
+            PrintStyledContent("Moves/pushes:".dark_grey().italic())
+        )?;
+        for (k, point) in self.front.iter().enumerate() {
+            let text = format!(" {}/{} ", point.moves, point.pushes);
+            queue!(
+                stdout(),
+                PrintStyledContent(match k == self.picked {
+                    true => text.black().on_grey(),
+                    false => text.dark_grey(),
+                })
+            )?;
+        }
+        queue!(
+            stdout(),
+            PrintStyledContent(" press <left>/<right> to pick one".dark_grey().italic())
+        )
+    }
+}
+
+/// `game_screen` only takes in `crossterm::event::Event`, thus we have to reconstruct it...
+fn command_event(command: BoardCommand) -> Event {
+    Event::Key(KeyEvent {
+        code: match command {
+            BoardCommand::Up => KeyCode::Up,
+            BoardCommand::Down => KeyCode::Down,
+            BoardCommand::Left => KeyCode::Left,
+            BoardCommand::Right => KeyCode::Right,
+            BoardCommand::Null => KeyCode::Null,
+        },
+        modifiers: KeyModifiers::NONE,
+        kind: KeyEventKind::Press,
+        state: KeyEventState::NONE,
+    })
+}
+
+/// One line telling why the solver came back without a solution.
+pub fn describe_error(e: &SolverError) -> StyledContent<String> {
+    match e {
+        SolverError::Unsolvable => style("No solution found by this search".to_string())
+            .red()
+            .bold(),
+        SolverError::UnreachableBox { .. } => style(e.to_string()).red().bold(),
+        SolverError::Cancelled => style("The search was cancelled".to_string())
+            .grey()
+            .italic(),
+        SolverError::LimitExceeded { .. } | SolverError::Io(_) => style(e.to_string()).yellow(),
+    }
 }
 
 impl PrintFullByQueue for SolverScreen {
     fn print_full(&self) -> Result<(), std::io::Error> {
-        self.game_screen.print_full()?;
+        self.game_screen.print_board()?;
+        if let Err(SolverError::UnreachableBox { pos: (i, j) }) = &self.sol {
+            // point at the culprit
+            queue!(
+                stdout(),
+                MoveTo(*j as u16, *i as u16),
+                PrintStyledContent(format!("{:?}", self.origin_game.cells[*i][*j]).on_red()),
+                MoveTo(0, self.origin_game.n as u16)
+            )?;
+        }
         queue!(
             stdout(),
             MoveToNextLine(1),
@@ -49,14 +247,79 @@ impl PrintFullByQueue for SolverScreen {
             },
             MoveToNextLine(1),
             PrintStyledContent(match &self.sol {
-                Ok(Solution { visited_states, .. }) => {
-                    let visited_str = format!("Visited {} states", visited_states);
-                    style(visited_str).dark_grey().italic()
+                Ok(Solution {
+                    seq,
+                    visited_states,
+                    pruned,
+                    optimal,
+                }) => {
+                    let pruned_str = pruned
+                        .iter()
+                        .map(|(kind, count)| format!("{} by {}", count, kind))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let visited_str = match pruned.is_empty() {
+                        true => format!("Visited {} states", visited_states),
+                        false => {
+                            format!("Visited {} states, pruned {}", visited_states, pruned_str)
+                        }
+                    };
+                    let optimal_str = match optimal {
+                        true => "optimal",
+                        false => "not proven optimal",
+                    };
+                    style(format!(
+                        "{} moves, {}. {}",
+                        seq.len(),
+                        optimal_str,
+                        visited_str
+                    ))
+                    .dark_grey()
+                    .italic()
                 }
-                Err(msg) => style(msg.to_owned()).red().bold(),
+                Err(e) => describe_error(e),
+            }),
+            MoveToNextLine(1),
+            PrintStyledContent(match (&self.improvements, &self.pending) {
+                (_, Some(pending)) => style(format!(
+                    "Found a shorter solution with {} moves, press <r> to replay it",
+                    pending.seq.len()
+                ))
+                .yellow(),
+                (Some(_), None) => style("Still searching for a shorter solution...".to_string())
+                    .dark_grey()
+                    .italic(),
+                (None, None) => style(self.note.clone().unwrap_or_default())
+                    .dark_grey()
+                    .italic(),
+            }),
+        )?;
+        self.print_front()?;
+        if self.by_push {
+            queue!(
+                stdout(),
+                MoveToNextLine(1),
+                PrintStyledContent(match &self.step {
+                    Some(step) => style(step.clone()).cyan(),
+                    None => style("Stepping by push".to_string()).cyan(),
+                })
+            )?;
+        }
+        queue!(
+            stdout(),
+            MoveToNextLine(1),
+            PrintStyledContent(match self.by_push {
+                true => "Press <space> to play up to the next box"
+                    .dark_grey()
+                    .italic(),
+                false => "Press <space> to start/pause playback".dark_grey().italic(),
             }),
             MoveToNextLine(1),
-            PrintStyledContent("Press <space> to start/pause playback".dark_grey().italic()),
+            PrintStyledContent(
+                "Press <s> to switch between stepping by push and by move"
+                    .dark_grey()
+                    .italic()
+            ),
             MoveToNextLine(1),
             PrintStyledContent("Press <q> to return to game play".dark_grey().italic()),
             MoveToNextLine(1),
@@ -71,25 +334,42 @@ impl Screen for SolverScreen {
         &mut self,
         event: Option<crossterm::event::Event>,
     ) -> super::screen::ScreenTransition {
-        let default_key_event = KeyEvent {
-            code: KeyCode::Null,
-            modifiers: KeyModifiers::NONE,
-            kind: KeyEventKind::Press,
-            state: KeyEventState::NONE,
-        };
+        self.poll_improvements();
         match event {
             Some(Event::Key(KeyEvent { code, .. })) => match code {
+                KeyCode::Char(' ') if self.by_push => {
+                    self.step_by_push();
+                    ScreenTransition::Continue
+                }
+                KeyCode::Char('s') => {
+                    self.by_push ^= true;
+                    self.play = false;
+                    let _ = queue!(stdout(), Clear(ClearType::All));
+                    let _ = self.print_full();
+                    ScreenTransition::Continue
+                }
                 KeyCode::Char(' ') => {
                     self.play ^= true;
                     ScreenTransition::Continue
                 }
-                KeyCode::Char('q') => ScreenTransition::Back,
+                KeyCode::Char('q') => {
+                    if let Some(cancel) = &self.cancel {
+                        cancel.cancel();
+                    }
+                    ScreenTransition::Back
+                }
                 KeyCode::Char('r') => {
-                    self.cur = 0;
-                    self.cur_update = 0;
-                    self.game_screen.g = self.origin_game.clone();
-                    let _ = queue!(stdout(), Clear(ClearType::All));
-                    let _ = self.print_full();
+                    self.adopt_pending();
+                    self.restart();
+                    ScreenTransition::Continue
+                }
+                KeyCode::Left | KeyCode::Right if !self.front.is_empty() => {
+                    self.picked = match code {
+                        KeyCode::Left => self.picked.saturating_sub(1),
+                        _ => (self.picked + 1).min(self.front.len() - 1),
+                    };
+                    self.set_sol(self.front[self.picked].solution.clone());
+                    self.restart();
                     ScreenTransition::Continue
                 }
                 _ => ScreenTransition::Continue,
@@ -99,29 +379,7 @@ impl Screen for SolverScreen {
                     Ok(Solution { seq, .. }) => {
                         if self.play && self.cur < seq.len() {
                             if self.cur_update == 0 {
-                                self.game_screen.update(match seq[self.cur] {
-                                    // `game_screen` only takes in `crossterm::event::Event`, thus we have to reconstruct it...
-                                    BoardCommand::Up => Some(Event::Key(KeyEvent {
-                                        code: KeyCode::Up,
-                                        ..default_key_event
-                                    })),
-                                    BoardCommand::Down => Some(Event::Key(KeyEvent {
-                                        code: KeyCode::Down,
-                                        ..default_key_event
-                                    })),
-                                    BoardCommand::Left => Some(Event::Key(KeyEvent {
-                                        code: KeyCode::Left,
-                                        ..default_key_event
-                                    })),
-                                    BoardCommand::Right => Some(Event::Key(KeyEvent {
-                                        code: KeyCode::Right,
-                                        ..default_key_event
-                                    })),
-                                    _ => Some(Event::Key(KeyEvent {
-                                        code: KeyCode::Null,
-                                        ..default_key_event
-                                    })),
-                                });
+                                self.game_screen.update(Some(command_event(seq[self.cur])));
                                 self.cur += 1;
                             }
                             self.cur_update += 1;